@@ -0,0 +1,492 @@
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+use crate::types::{duration_ms, percentile, RequestResult};
+
+/// Live metric an abort condition watches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AbortMetric {
+    /// Percentage of requests that failed
+    ErrorRate,
+    P95,
+    P99,
+}
+
+impl AbortMetric {
+    fn key(&self) -> &'static str {
+        match self {
+            Self::ErrorRate => "error_rate",
+            Self::P95 => "p95_ms",
+            Self::P99 => "p99_ms",
+        }
+    }
+
+    fn unit(&self) -> &'static str {
+        match self {
+            Self::ErrorRate => "%",
+            Self::P95 | Self::P99 => "ms",
+        }
+    }
+}
+
+/// Stop the run when `metric` stays above `limit` over the trailing window,
+/// written as `error_rate>50@30s` or `p99_ms>5000@60s`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct AbortCondition {
+    pub metric: AbortMetric,
+    pub limit: f64,
+    pub window: Duration,
+}
+
+impl fmt::Display for AbortCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}>{}@{}s",
+            self.metric.key(),
+            self.limit,
+            self.window.as_secs()
+        )
+    }
+}
+
+impl FromStr for AbortCondition {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid abort condition '{}', expected METRIC>LIMIT@SECONDSs such as error_rate>50@30s",
+                value
+            )
+        };
+        let (metric, rest) = value.split_once('>').ok_or_else(invalid)?;
+        let (limit, window) = rest.split_once('@').ok_or_else(invalid)?;
+
+        let metric = match metric.trim() {
+            "error_rate" => AbortMetric::ErrorRate,
+            "p95_ms" => AbortMetric::P95,
+            "p99_ms" => AbortMetric::P99,
+            other => {
+                return Err(format!(
+                    "unknown abort metric '{}' (expected error_rate, p95_ms or p99_ms)",
+                    other
+                ))
+            }
+        };
+        let limit: f64 = limit.trim().parse().map_err(|_| invalid())?;
+        let window_secs: u64 = window
+            .trim()
+            .trim_end_matches('s')
+            .parse()
+            .map_err(|_| invalid())?;
+        if !limit.is_finite() || limit < 0.0 || window_secs == 0 {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            metric,
+            limit,
+            window: Duration::from_secs(window_secs),
+        })
+    }
+}
+
+impl TryFrom<String> for AbortCondition {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<AbortCondition> for String {
+    fn from(condition: AbortCondition) -> Self {
+        condition.to_string()
+    }
+}
+
+/// Circuit breaker settings:
+///
+/// ```toml
+/// [abort]
+/// conditions = ["error_rate>50@30s", "p99_ms>5000@60s"]
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AbortConfig {
+    pub conditions: Vec<AbortCondition>,
+    /// Requests a window needs before its conditions are checked
+    pub min_requests: usize,
+}
+
+impl Default for AbortConfig {
+    fn default() -> Self {
+        Self {
+            conditions: Vec::new(),
+            min_requests: 20,
+        }
+    }
+}
+
+/// Why and when a run stopped before finishing its load profile
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Abort {
+    pub reason: String,
+    /// When the run was stopped, relative to its start
+    #[serde(with = "duration_ms")]
+    pub at: Duration,
+    /// Scenarios that were scheduled but never started
+    pub skipped_scenarios: usize,
+    /// A signal cut in-flight scenarios short, straight to their cleanup steps
+    #[serde(default)]
+    pub interrupted: bool,
+    /// A sequential or unique feeder ran out of rows; no abort condition tripped
+    #[serde(default)]
+    pub exhausted: bool,
+}
+
+impl Abort {
+    /// `interrupted`, `stopped (out of test data)` or `aborted`
+    pub fn outcome(&self) -> &'static str {
+        if self.interrupted {
+            "interrupted"
+        } else if self.exhausted {
+            "stopped (out of test data)"
+        } else {
+            "aborted"
+        }
+    }
+}
+
+/// Shared flag that tells the scheduler to stop starting scenarios; the first
+/// reason given wins
+pub struct StopSignal {
+    sender: watch::Sender<Option<(String, Duration)>>,
+    started: Instant,
+    interrupted: AtomicBool,
+    exhausted: AtomicBool,
+}
+
+impl StopSignal {
+    pub fn new(started: Instant) -> Self {
+        Self {
+            sender: watch::Sender::new(None),
+            started,
+            interrupted: AtomicBool::new(false),
+            exhausted: AtomicBool::new(false),
+        }
+    }
+
+    /// Stop, and also have in-flight scenarios skip to their cleanup steps
+    pub fn interrupt(&self, reason: String) {
+        self.interrupted.store(true, Ordering::Relaxed);
+        self.stop(reason);
+    }
+
+    pub fn is_interrupted(&self) -> bool {
+        self.interrupted.load(Ordering::Relaxed)
+    }
+
+    /// Stop because test data ran out, unless something else stopped the run first
+    pub fn exhaust(&self, reason: String) {
+        if self.stop(reason) {
+            self.exhausted.store(true, Ordering::Relaxed);
+        }
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.exhausted.load(Ordering::Relaxed)
+    }
+
+    /// Whether this was the first stop
+    pub fn stop(&self, reason: String) -> bool {
+        let at = self.started.elapsed();
+        self.sender.send_if_modified(|state| {
+            if state.is_some() {
+                return false;
+            }
+            *state = Some((reason, at));
+            true
+        })
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.sender.borrow().is_some()
+    }
+
+    /// The reason and time of the stop, if there was one
+    pub fn reason(&self) -> Option<(String, Duration)> {
+        self.sender.borrow().clone()
+    }
+
+    /// Resolve once the run has been stopped
+    pub async fn stopped(&self) {
+        let mut receiver = self.sender.subscribe();
+        let _ = receiver.wait_for(Option::is_some).await;
+    }
+}
+
+/// One finished request in the live window
+struct Sample {
+    finished: Duration,
+    response_time: Duration,
+    success: bool,
+}
+
+/// Trailing window of finished requests, checked against the abort conditions
+pub struct CircuitBreaker {
+    config: AbortConfig,
+    started: Instant,
+    /// Samples older than this are never needed again
+    horizon: Duration,
+    samples: Mutex<VecDeque<Sample>>,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: &AbortConfig, started: Instant) -> Self {
+        Self {
+            horizon: config
+                .conditions
+                .iter()
+                .map(|condition| condition.window)
+                .max()
+                .unwrap_or_default(),
+            config: config.clone(),
+            started,
+            samples: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.config.conditions.is_empty()
+    }
+
+    pub fn record(&self, result: &RequestResult) {
+        if !self.is_enabled() {
+            return;
+        }
+        let finished = self.started.elapsed();
+        let mut samples = self.samples.lock().unwrap();
+        samples.push_back(Sample {
+            finished,
+            response_time: result.response_time,
+            success: result.success,
+        });
+        while samples
+            .front()
+            .is_some_and(|sample| sample.finished + self.horizon < finished)
+        {
+            samples.pop_front();
+        }
+    }
+
+    /// The first condition that currently holds, described for the report
+    pub fn check(&self) -> Option<String> {
+        let now = self.started.elapsed();
+        let samples = self.samples.lock().unwrap();
+
+        self.config.conditions.iter().find_map(|condition| {
+            // A condition only applies once the run has lasted a whole window
+            let from = now.checked_sub(condition.window)?;
+            let window: Vec<&Sample> = samples
+                .iter()
+                .filter(|sample| sample.finished >= from)
+                .collect();
+            if window.len() < self.config.min_requests.max(1) {
+                return None;
+            }
+
+            let value = match condition.metric {
+                AbortMetric::ErrorRate => {
+                    let errors = window.iter().filter(|sample| !sample.success).count();
+                    errors as f64 / window.len() as f64 * 100.0
+                }
+                AbortMetric::P95 | AbortMetric::P99 => {
+                    let mut response_times: Vec<Duration> = window
+                        .iter()
+                        .filter(|sample| sample.success)
+                        .map(|sample| sample.response_time)
+                        .collect();
+                    response_times.sort();
+                    let pct = if condition.metric == AbortMetric::P95 {
+                        95.0
+                    } else {
+                        99.0
+                    };
+                    percentile(&response_times, pct).as_secs_f64() * 1000.0
+                }
+            };
+
+            (value > condition.limit).then(|| {
+                format!(
+                    "{} {:.1}{} > {}{} over the last {}s ({} requests)",
+                    condition.metric.key(),
+                    value,
+                    condition.metric.unit(),
+                    condition.limit,
+                    condition.metric.unit(),
+                    condition.window.as_secs(),
+                    window.len()
+                )
+            })
+        })
+    }
+}
+
+/// Ctrl-C, and SIGTERM on Unix, registered once so a signal sent between two
+/// `recv` calls is still delivered
+pub struct Signals {
+    #[cfg(unix)]
+    interrupt: tokio::signal::unix::Signal,
+    #[cfg(unix)]
+    terminate: tokio::signal::unix::Signal,
+}
+
+impl Signals {
+    pub fn new() -> std::io::Result<Self> {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+            Ok(Self {
+                interrupt: signal(SignalKind::interrupt())?,
+                terminate: signal(SignalKind::terminate())?,
+            })
+        }
+        #[cfg(not(unix))]
+        {
+            Ok(Self {})
+        }
+    }
+
+    /// Wait for the next signal; returns its name
+    pub async fn recv(&mut self) -> &'static str {
+        #[cfg(unix)]
+        {
+            tokio::select! {
+                _ = self.interrupt.recv() => "SIGINT",
+                _ = self.terminate.recv() => "SIGTERM",
+            }
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = tokio::signal::ctrl_c().await;
+            "SIGINT"
+        }
+    }
+}
+
+pub fn display_abort(abort: &Abort) {
+    println!(
+        "{}",
+        format!(
+            "\n🛑 Run {} at {:.0}s: {}",
+            abort.outcome(),
+            abort.at.as_secs_f64(),
+            abort.reason
+        )
+        .red()
+        .bold()
+    );
+    if abort.skipped_scenarios > 0 {
+        println!(
+            "{}",
+            format!(
+                "   {} scheduled scenarios never started; results cover the scenarios that ran",
+                abort.skipped_scenarios
+            )
+            .red()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(conditions: &[&str], min_requests: usize) -> AbortConfig {
+        AbortConfig {
+            conditions: conditions.iter().map(|c| c.parse().unwrap()).collect(),
+            min_requests,
+        }
+    }
+
+    fn result(success: bool, millis: u64) -> RequestResult {
+        RequestResult {
+            success,
+            response_time: Duration::from_millis(millis),
+            ..RequestResult::default()
+        }
+    }
+
+    /// A breaker whose run started a minute ago, so 30s windows are complete
+    fn breaker(config: &AbortConfig) -> CircuitBreaker {
+        let started = Instant::now().checked_sub(Duration::from_secs(60)).unwrap();
+        CircuitBreaker::new(config, started)
+    }
+
+    #[test]
+    fn parses_and_prints_conditions() {
+        let condition: AbortCondition = "p99_ms > 5000 @ 60s".parse().unwrap();
+        assert_eq!(condition.metric, AbortMetric::P99);
+        assert_eq!(condition.window, Duration::from_secs(60));
+        assert_eq!(condition.to_string(), "p99_ms>5000@60s");
+
+        for invalid in [
+            "error_rate>50",
+            "error_rate>-1@30s",
+            "error_rate>50@0s",
+            "p50_ms>1@5s",
+        ] {
+            assert!(invalid.parse::<AbortCondition>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn error_rate_trips_once_the_window_has_enough_requests() {
+        let breaker = breaker(&config(&["error_rate>50@30s"], 4));
+        for _ in 0..3 {
+            breaker.record(&result(false, 10));
+        }
+        assert_eq!(breaker.check(), None);
+
+        breaker.record(&result(true, 10));
+        let reason = breaker.check().unwrap();
+        assert!(reason.starts_with("error_rate 75.0% > 50%"), "{}", reason);
+    }
+
+    #[test]
+    fn latency_conditions_ignore_failed_requests() {
+        let breaker = breaker(&config(&["p95_ms>500@30s"], 1));
+        breaker.record(&result(true, 100));
+        breaker.record(&result(false, 9000));
+        assert_eq!(breaker.check(), None);
+
+        breaker.record(&result(true, 900));
+        assert!(breaker.check().unwrap().starts_with("p95_ms 900.0ms"));
+    }
+
+    #[test]
+    fn conditions_wait_for_a_whole_window() {
+        let breaker = CircuitBreaker::new(&config(&["error_rate>0@30s"], 1), Instant::now());
+        breaker.record(&result(false, 10));
+        assert_eq!(breaker.check(), None);
+    }
+
+    #[test]
+    fn the_first_stop_reason_wins() {
+        let stop = StopSignal::new(Instant::now());
+        assert!(stop.stop("error_rate".to_string()));
+        assert!(!stop.stop("p99_ms".to_string()));
+        assert!(stop.is_stopped());
+        assert_eq!(stop.reason().unwrap().0, "error_rate");
+    }
+}
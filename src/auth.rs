@@ -0,0 +1,415 @@
+use aws_config::SdkConfig;
+use aws_credential_types::provider::{ProvideCredentials, SharedCredentialsProvider};
+use aws_credential_types::Credentials;
+use aws_sigv4::http_request::{sign, SignableBody, SignableRequest, SigningSettings};
+use aws_sigv4::sign::v4;
+use base64::Engine;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::Mutex;
+
+/// Tokens and credentials are renewed this long before they expire
+const REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+/// Lifetime assumed for a token response without `expires_in`
+const DEFAULT_TOKEN_LIFETIME: Duration = Duration::from_secs(300);
+
+/// A secret written in the config file, or read from an environment variable
+/// when the run starts:
+///
+/// ```toml
+/// token = "abc123"
+/// token = { env = "PETFOOD_TOKEN" }
+/// ```
+#[derive(Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Secret {
+    Value(String),
+    Env { env: String },
+}
+
+impl Secret {
+    fn resolve(&self, field: &str) -> anyhow::Result<String> {
+        match self {
+            Self::Value(value) => Ok(value.clone()),
+            Self::Env { env } => std::env::var(env)
+                .map_err(|_| anyhow::anyhow!("{} reads ${}, which is not set", field, env)),
+        }
+    }
+}
+
+/// Config printouts and saved reports never show a secret written in the file
+impl Serialize for Secret {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Value(_) => serializer.serialize_str("<redacted>"),
+            Self::Env { env } => {
+                use serde::ser::SerializeMap;
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry("env", env)?;
+                map.end()
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Value(_) => write!(f, "<redacted>"),
+            Self::Env { env } => write!(f, "${}", env),
+        }
+    }
+}
+
+/// How requests to one service authenticate:
+///
+/// ```toml
+/// [auth.petfood]
+/// type = "oauth2"
+/// token_url = "https://auth.example.com/oauth2/token"
+/// client_id = "load-tester"
+/// client_secret = { env = "PETFOOD_CLIENT_SECRET" }
+/// scope = "petfood/read"
+///
+/// [auth.statusupdater]
+/// type = "sigv4"
+/// service = "execute-api"
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum AuthConfig {
+    /// `Authorization: Bearer <token>`
+    Bearer { token: Secret },
+    /// The key in a header, `x-api-key` unless set
+    ApiKey {
+        #[serde(default = "default_api_key_header")]
+        header: String,
+        key: Secret,
+    },
+    /// `Authorization: Basic ...`
+    Basic { username: String, password: Secret },
+    /// Tokens from the OAuth2 client credentials grant, cached until shortly
+    /// before they expire and fetched again after a 401
+    Oauth2 {
+        token_url: String,
+        client_id: String,
+        client_secret: Secret,
+        scope: Option<String>,
+        audience: Option<String>,
+    },
+    /// AWS SigV4 signing with the credentials the SSM client uses, for IAM-authorized
+    /// API Gateway (`execute-api`) or Lambda function URLs (`lambda`)
+    Sigv4 {
+        #[serde(default = "default_sigv4_service")]
+        service: String,
+        /// Defaults to the run's region
+        region: Option<String>,
+    },
+}
+
+fn default_api_key_header() -> String {
+    "x-api-key".to_string()
+}
+
+fn default_sigv4_service() -> String {
+    "execute-api".to_string()
+}
+
+impl AuthConfig {
+    /// The `type` key, e.g. `oauth2`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Bearer { .. } => "bearer",
+            Self::ApiKey { .. } => "api_key",
+            Self::Basic { .. } => "basic",
+            Self::Oauth2 { .. } => "oauth2",
+            Self::Sigv4 { .. } => "sigv4",
+        }
+    }
+
+    /// Whether requests are signed with AWS credentials
+    pub fn needs_aws(&self) -> bool {
+        matches!(self, Self::Sigv4 { .. })
+    }
+
+    pub fn validate(&self, service: &str) -> anyhow::Result<()> {
+        match self {
+            Self::ApiKey { header, .. } => {
+                reqwest::header::HeaderName::from_bytes(header.as_bytes()).map_err(|_| {
+                    anyhow::anyhow!("auth.{}.header: '{}' is not a header name", service, header)
+                })?;
+            }
+            Self::Oauth2 { token_url, .. }
+                if !token_url.starts_with("http://") && !token_url.starts_with("https://") =>
+            {
+                anyhow::bail!("auth.{}.token_url must be an http(s) URL", service);
+            }
+            Self::Sigv4 { service: name, .. } if name.is_empty() => {
+                anyhow::bail!(
+                    "auth.{}.service must name the AWS service to sign for",
+                    service
+                );
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Every secret, read now so a missing environment variable fails the run up front
+    pub fn check_secrets(&self, service: &str) -> anyhow::Result<()> {
+        match self {
+            Self::Bearer { token } => token.resolve(&format!("auth.{}.token", service))?,
+            Self::ApiKey { key, .. } => key.resolve(&format!("auth.{}.key", service))?,
+            Self::Basic { password, .. } => {
+                password.resolve(&format!("auth.{}.password", service))?
+            }
+            Self::Oauth2 { client_secret, .. } => {
+                client_secret.resolve(&format!("auth.{}.client_secret", service))?
+            }
+            Self::Sigv4 { .. } => String::new(),
+        };
+        Ok(())
+    }
+}
+
+/// Credentials for every service that has them, applied to each request
+pub struct Auth {
+    services: HashMap<String, ServiceAuth>,
+}
+
+enum ServiceAuth {
+    /// Bearer, API key and basic auth: one fixed header
+    Header(String, String),
+    OAuth2(OAuth2),
+    SigV4(SigV4),
+}
+
+struct OAuth2 {
+    client: Client,
+    token_url: String,
+    client_id: String,
+    client_secret: String,
+    scope: Option<String>,
+    audience: Option<String>,
+    /// The current token and when to renew it; held while fetching so users
+    /// waiting on the same service share one token request
+    token: Mutex<Option<(String, Instant)>>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: Option<u64>,
+}
+
+struct SigV4 {
+    service: String,
+    region: String,
+    provider: SharedCredentialsProvider,
+    credentials: Mutex<Option<Credentials>>,
+}
+
+impl Auth {
+    /// Resolve every secret; `aws` is needed only for SigV4
+    pub fn new(
+        config: &BTreeMap<String, AuthConfig>,
+        region: &str,
+        timeout: Duration,
+        aws: Option<&SdkConfig>,
+    ) -> anyhow::Result<Self> {
+        let mut services = HashMap::new();
+        for (service, auth) in config {
+            let secret = |secret: &Secret, field: &str| {
+                secret.resolve(&format!("auth.{}.{}", service, field))
+            };
+            let service_auth = match auth {
+                AuthConfig::Bearer { token } => ServiceAuth::Header(
+                    "Authorization".to_string(),
+                    format!("Bearer {}", secret(token, "token")?),
+                ),
+                AuthConfig::ApiKey { header, key } => {
+                    ServiceAuth::Header(header.clone(), secret(key, "key")?)
+                }
+                AuthConfig::Basic { username, password } => {
+                    let credentials = format!("{}:{}", username, secret(password, "password")?);
+                    ServiceAuth::Header(
+                        "Authorization".to_string(),
+                        format!(
+                            "Basic {}",
+                            base64::engine::general_purpose::STANDARD.encode(credentials)
+                        ),
+                    )
+                }
+                AuthConfig::Oauth2 {
+                    token_url,
+                    client_id,
+                    client_secret,
+                    scope,
+                    audience,
+                } => ServiceAuth::OAuth2(OAuth2 {
+                    client: Client::builder().timeout(timeout).build()?,
+                    token_url: token_url.clone(),
+                    client_id: client_id.clone(),
+                    client_secret: secret(client_secret, "client_secret")?,
+                    scope: scope.clone(),
+                    audience: audience.clone(),
+                    token: Mutex::new(None),
+                }),
+                AuthConfig::Sigv4 {
+                    service: name,
+                    region: signing_region,
+                } => {
+                    let provider =
+                        aws.and_then(SdkConfig::credentials_provider)
+                            .ok_or_else(|| {
+                                anyhow::anyhow!(
+                                "auth.{} signs with SigV4 but no AWS credentials are configured",
+                                service
+                            )
+                            })?;
+                    ServiceAuth::SigV4(SigV4 {
+                        service: name.clone(),
+                        region: signing_region.clone().unwrap_or_else(|| region.to_string()),
+                        provider,
+                        credentials: Mutex::new(None),
+                    })
+                }
+            };
+            services.insert(service.clone(), service_auth);
+        }
+        Ok(Self { services })
+    }
+
+    /// Headers that authenticate a request to `service`; SigV4 signs `url` and `body`
+    pub async fn headers(
+        &self,
+        service: &str,
+        method: &str,
+        url: &str,
+        body: Option<&[u8]>,
+    ) -> Result<Vec<(String, String)>, String> {
+        match self.services.get(service) {
+            None => Ok(Vec::new()),
+            Some(ServiceAuth::Header(name, value)) => Ok(vec![(name.clone(), value.clone())]),
+            Some(ServiceAuth::OAuth2(oauth2)) => Ok(vec![(
+                "Authorization".to_string(),
+                format!("Bearer {}", oauth2.token().await?),
+            )]),
+            Some(ServiceAuth::SigV4(sigv4)) => sigv4.sign(method, url, body).await,
+        }
+    }
+
+    /// Forget a rejected OAuth2 token so the next request fetches a new one
+    pub async fn rejected(&self, service: &str) {
+        if let Some(ServiceAuth::OAuth2(oauth2)) = self.services.get(service) {
+            *oauth2.token.lock().await = None;
+        }
+    }
+}
+
+impl OAuth2 {
+    async fn token(&self) -> Result<String, String> {
+        let mut token = self.token.lock().await;
+        if let Some((value, renew_at)) = token.as_ref() {
+            if Instant::now() < *renew_at {
+                return Ok(value.clone());
+            }
+        }
+
+        let mut form = vec![("grant_type", "client_credentials")];
+        if let Some(scope) = &self.scope {
+            form.push(("scope", scope));
+        }
+        if let Some(audience) = &self.audience {
+            form.push(("audience", audience));
+        }
+        let response = self
+            .client
+            .post(&self.token_url)
+            .basic_auth(&self.client_id, Some(&self.client_secret))
+            .form(&form)
+            .send()
+            .await
+            .map_err(|err| format!("token request to {} failed: {}", self.token_url, err))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(format!(
+                "token request to {} failed: HTTP {}",
+                self.token_url,
+                status.as_u16()
+            ));
+        }
+        let response: TokenResponse = response
+            .json()
+            .await
+            .map_err(|err| format!("unreadable token response: {}", err))?;
+
+        let lifetime = response
+            .expires_in
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_TOKEN_LIFETIME);
+        // Short-lived tokens are renewed after 90% of their life instead
+        let renew_at = Instant::now() + lifetime - REFRESH_MARGIN.min(lifetime / 10);
+        *token = Some((response.access_token.clone(), renew_at));
+        Ok(response.access_token)
+    }
+}
+
+impl SigV4 {
+    async fn credentials(&self) -> Result<Credentials, String> {
+        let mut credentials = self.credentials.lock().await;
+        let fresh = credentials.as_ref().is_some_and(|current| {
+            current
+                .expiry()
+                .is_none_or(|expiry| SystemTime::now() + REFRESH_MARGIN < expiry)
+        });
+        if !fresh {
+            *credentials = Some(
+                self.provider
+                    .provide_credentials()
+                    .await
+                    .map_err(|err| format!("no AWS credentials for SigV4: {}", err))?,
+            );
+        }
+        Ok(credentials.clone().unwrap())
+    }
+
+    async fn sign(
+        &self,
+        method: &str,
+        url: &str,
+        body: Option<&[u8]>,
+    ) -> Result<Vec<(String, String)>, String> {
+        let identity = self.credentials().await?.into();
+        let params = v4::SigningParams::builder()
+            .identity(&identity)
+            .region(&self.region)
+            .name(&self.service)
+            .time(SystemTime::now())
+            .settings(SigningSettings::default())
+            .build()
+            .map_err(|err| format!("SigV4 signing failed: {}", err))?
+            .into();
+        // Sign the URL as it goes on the wire, percent-encoded
+        let url = reqwest::Url::parse(url).map_err(|err| format!("invalid URL: {}", err))?;
+        let headers = body.map(|_| ("content-type", "application/json"));
+        let request = SignableRequest::new(
+            method,
+            url.as_str(),
+            headers.into_iter(),
+            SignableBody::Bytes(body.unwrap_or_default()),
+        )
+        .map_err(|err| format!("SigV4 signing failed: {}", err))?;
+        let (instructions, _) = sign(request, &params)
+            .map_err(|err| format!("SigV4 signing failed: {}", err))?
+            .into_parts();
+        Ok(instructions
+            .headers()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect())
+    }
+}
@@ -0,0 +1,329 @@
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::errors::ErrorKind;
+use crate::report::RunReport;
+use crate::template::Vars;
+use crate::types::{duration_ms, RequestResult};
+
+/// Users listed when some failed to clean up
+const MAX_LISTED_USERS: usize = 10;
+
+/// The cleanup phase that runs after the load, for every user that changed state:
+///
+/// ```toml
+/// [cleanup]
+/// concurrency = 10
+/// users_file = "results/touched-users.txt"
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CleanupConfig {
+    /// Run the cleanup phase at the end of the run
+    pub enabled: bool,
+    /// Users cleaned up at the same time
+    pub concurrency: usize,
+    /// Every user that changed state is appended here as the run goes, so a
+    /// crashed run can be purged with `cleanup --users-from`
+    pub users_file: Option<PathBuf>,
+}
+
+impl Default for CleanupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            concurrency: 10,
+            users_file: None,
+        }
+    }
+}
+
+impl CleanupConfig {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.concurrency == 0 {
+            anyhow::bail!("cleanup.concurrency must be at least 1");
+        }
+        Ok(())
+    }
+}
+
+/// Users that changed state and have not been cleaned up yet. Each of a user's
+/// concurrent scenarios is kept apart, with the values it captured, so one
+/// finishing its cleanup doesn't clear the user while another is still changing
+/// things, and the cleanup phase can undo each with its own ids.
+pub struct CleanupTracker {
+    /// User id -> the captures of each scenario whose changes are still in place
+    pending: Mutex<BTreeMap<String, BTreeMap<usize, Vars>>>,
+    next_scenario: AtomicUsize,
+    /// Every user ever marked, one id per line
+    users_file: Option<Mutex<File>>,
+}
+
+impl CleanupTracker {
+    pub fn new(users_file: Option<&Path>) -> anyhow::Result<Self> {
+        let users_file = users_file
+            .map(|path| {
+                File::create(path)
+                    .map(Mutex::new)
+                    .map_err(|err| anyhow::anyhow!("Failed to create {}: {}", path.display(), err))
+            })
+            .transpose()?;
+        Ok(Self {
+            pending: Mutex::new(BTreeMap::new()),
+            next_scenario: AtomicUsize::new(0),
+            users_file,
+        })
+    }
+
+    /// Record that one of `user_id`'s scenarios is about to change state; call
+    /// once per scenario and pass the returned key to `captured` and `cleaned`
+    pub fn touched(&self, user_id: &str) -> usize {
+        let scenario = self.next_scenario.fetch_add(1, Ordering::Relaxed);
+        let added = {
+            let mut pending = self.pending.lock().unwrap();
+            let scenarios = pending.entry(user_id.to_string()).or_default();
+            scenarios.insert(scenario, Vars::new());
+            scenarios.len() == 1
+        };
+        if let (true, Some(file)) = (added, &self.users_file) {
+            // Written straight away so the list survives a crash
+            let mut file = file.lock().unwrap();
+            let _ = writeln!(file, "{}", user_id);
+            let _ = file.flush();
+        }
+        scenario
+    }
+
+    /// Keep what a scenario has captured so far, for its cleanup steps
+    pub fn captured(&self, user_id: &str, scenario: usize, vars: &Vars) {
+        let mut pending = self.pending.lock().unwrap();
+        if let Some(captures) = pending
+            .get_mut(user_id)
+            .and_then(|scenarios| scenarios.get_mut(&scenario))
+        {
+            captures.clone_from(vars);
+        }
+    }
+
+    /// Record that one scenario removed what it changed
+    pub fn cleaned(&self, user_id: &str, scenario: usize) {
+        let mut pending = self.pending.lock().unwrap();
+        if let Some(scenarios) = pending.get_mut(user_id) {
+            scenarios.remove(&scenario);
+            if scenarios.is_empty() {
+                pending.remove(user_id);
+            }
+        }
+    }
+
+    /// Record that everything `user_id` changed has been removed, by every scenario
+    pub fn cleared(&self, user_id: &str) {
+        self.pending.lock().unwrap().remove(user_id);
+    }
+
+    pub fn pending(&self) -> Vec<String> {
+        self.pending.lock().unwrap().keys().cloned().collect()
+    }
+
+    /// What each of `user_id`'s unfinished scenarios captured; empty for users
+    /// this run didn't track, e.g. ones read from a file
+    pub fn captures(&self, user_id: &str) -> Vec<Vars> {
+        self.pending
+            .lock()
+            .unwrap()
+            .get(user_id)
+            .map(|scenarios| scenarios.values().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Outcome of the cleanup phase
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CleanupResult {
+    /// Users that still had state to remove when the phase started
+    pub users: Vec<String>,
+    pub requests: Vec<RequestResult>,
+    #[serde(with = "duration_ms")]
+    pub duration: Duration,
+}
+
+impl CleanupResult {
+    /// Users with at least one failed cleanup request, which may have left data
+    /// behind; trying again may clean them up
+    pub fn failed_users(&self) -> Vec<&str> {
+        self.users_where(|request| {
+            !request.success && request.error_kind != Some(ErrorKind::Skipped)
+        })
+    }
+
+    /// Users with a cleanup step that needs a value their scenario never
+    /// captured; trying again can't clean them up
+    pub fn uncleanable_users(&self) -> Vec<&str> {
+        self.users_where(|request| request.error_kind == Some(ErrorKind::Skipped))
+    }
+
+    fn users_where(&self, predicate: impl Fn(&RequestResult) -> bool) -> Vec<&str> {
+        let mut users: Vec<&str> = self
+            .requests
+            .iter()
+            .filter(|request| predicate(request))
+            .map(|request| request.user_id.as_str())
+            .collect();
+        users.dedup();
+        users
+    }
+}
+
+/// User ids to clean up: a `users_file` (one id per line) or a saved JSON/NDJSON
+/// report, from which the users its run left uncleaned are taken
+pub fn load_users(path: &Path) -> anyhow::Result<Vec<String>> {
+    let is_report = path.extension().is_some_and(|extension| {
+        extension == "json" || extension == "ndjson" || extension == "jsonl"
+    });
+
+    let users: BTreeSet<String> = if is_report {
+        RunReport::load(path)?.uncleaned_users.into_iter().collect()
+    } else {
+        std::fs::read_to_string(path)
+            .map_err(|err| anyhow::anyhow!("Failed to read {}: {}", path.display(), err))?
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect()
+    };
+    Ok(users.into_iter().collect())
+}
+
+pub fn display_cleanup(cleanup: &CleanupResult) {
+    println!("{}", "\n🧹 Cleanup Phase".blue().bold());
+    if cleanup.users.is_empty() {
+        println!("{}", "   Nothing left to clean up".green());
+        return;
+    }
+
+    let failed = cleanup.requests.iter().filter(|r| !r.success).count();
+    println!(
+        "   {} users, {} requests ({} failed) in {:.1}s",
+        cleanup.users.len(),
+        cleanup.requests.len(),
+        failed,
+        cleanup.duration.as_secs_f64()
+    );
+
+    let failed_users = cleanup.failed_users();
+    let uncleanable_users = cleanup.uncleanable_users();
+    if failed_users.is_empty() && uncleanable_users.is_empty() {
+        println!("{}", "   ✓ All users cleaned up".green());
+        return;
+    }
+    if !uncleanable_users.is_empty() {
+        println!(
+            "{}",
+            format!(
+                "   ✗ {} users can't be cleaned up (a value their cleanup needs was never captured): {}",
+                uncleanable_users.len(),
+                list_users(&uncleanable_users)
+            )
+            .red()
+        );
+    }
+    if !failed_users.is_empty() {
+        println!(
+            "{}",
+            format!(
+                "   ✗ {} users may still have data: {}",
+                failed_users.len(),
+                list_users(&failed_users)
+            )
+            .red()
+        );
+        println!(
+            "{}",
+            "   Retry with: load-tester cleanup --users-from <report or users file>".bright_black()
+        );
+    }
+}
+
+fn list_users(users: &[&str]) -> String {
+    let mut listed = users
+        .iter()
+        .take(MAX_LISTED_USERS)
+        .copied()
+        .collect::<Vec<_>>()
+        .join(", ");
+    if users.len() > MAX_LISTED_USERS {
+        listed.push_str(", ...");
+    }
+    listed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn result(user_id: &str, success: bool, error_kind: Option<ErrorKind>) -> RequestResult {
+        RequestResult {
+            step: "undo".to_string(),
+            method: "DELETE".to_string(),
+            url: "http://localhost/items/1".to_string(),
+            user_id: user_id.to_string(),
+            success,
+            error_kind,
+            ..RequestResult::default()
+        }
+    }
+
+    #[test]
+    fn user_stays_pending_until_every_scenario_is_cleaned() {
+        let tracker = CleanupTracker::new(None).unwrap();
+        let first = tracker.touched("u1");
+        let second = tracker.touched("u1");
+        tracker.cleaned("u1", first);
+        assert_eq!(tracker.pending(), vec!["u1"]);
+        tracker.cleaned("u1", second);
+        assert!(tracker.pending().is_empty());
+    }
+
+    #[test]
+    fn keeps_each_scenarios_captures() {
+        let tracker = CleanupTracker::new(None).unwrap();
+        let first = tracker.touched("u1");
+        let second = tracker.touched("u1");
+        tracker.captured("u1", first, &Vars::from([("id".to_string(), "a".into())]));
+        tracker.captured("u1", second, &Vars::from([("id".to_string(), "b".into())]));
+        let ids: Vec<_> = tracker
+            .captures("u1")
+            .iter()
+            .map(|vars| vars["id"].clone())
+            .collect();
+        assert_eq!(ids, vec!["a", "b"]);
+        assert!(tracker.captures("u2").is_empty());
+
+        tracker.cleared("u1");
+        assert!(tracker.pending().is_empty());
+    }
+
+    #[test]
+    fn skipped_steps_make_users_uncleanable_not_failed() {
+        let cleanup = CleanupResult {
+            users: vec!["u1".to_string(), "u2".to_string(), "u3".to_string()],
+            requests: vec![
+                result("u1", false, Some(ErrorKind::Http(500))),
+                result("u2", false, Some(ErrorKind::Skipped)),
+                result("u3", true, None),
+            ],
+            duration: Duration::ZERO,
+        };
+        assert_eq!(cleanup.failed_users(), vec!["u1"]);
+        assert_eq!(cleanup.uncleanable_users(), vec!["u2"]);
+    }
+}
@@ -0,0 +1,534 @@
+use colored::*;
+use serde::{Deserialize, Serialize};
+
+use crate::report::RunReport;
+use crate::types::{LoadTestResults, StepStats};
+
+/// Fewer latency samples than this on either side and no significance test is run
+const MIN_SAMPLES: usize = 8;
+
+/// When a difference from the baseline run counts as a regression:
+///
+/// ```toml
+/// baseline = "results/last-deploy.json"
+///
+/// [regression]
+/// tolerance_pct = 10.0
+/// max_error_rate_increase = 1.0
+/// alpha = 0.05
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RegressionConfig {
+    /// Largest allowed p50/p95/p99 latency increase, and run throughput drop, in percent
+    pub tolerance_pct: f64,
+    /// Largest allowed error rate increase in percentage points
+    pub max_error_rate_increase: f64,
+    /// Significance level for the Mann-Whitney test on latency samples
+    pub alpha: f64,
+}
+
+impl Default for RegressionConfig {
+    fn default() -> Self {
+        Self {
+            tolerance_pct: 10.0,
+            max_error_rate_increase: 1.0,
+            alpha: 0.05,
+        }
+    }
+}
+
+/// A metric that got worse than the baseline by more than the tolerance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Regression {
+    /// `run` or the step name
+    pub scope: String,
+    pub metric: String,
+    pub baseline: f64,
+    pub candidate: f64,
+    /// One-sided Mann-Whitney p-value, for latency metrics
+    pub p_value: Option<f64>,
+}
+
+/// One metric in the baseline and candidate runs
+#[derive(Debug, Clone, Copy)]
+pub struct MetricDelta {
+    pub baseline: f64,
+    pub candidate: f64,
+}
+
+impl MetricDelta {
+    /// Relative change in percent, `None` when the baseline is zero
+    pub fn change_pct(&self) -> Option<f64> {
+        if self.baseline == 0.0 {
+            None
+        } else {
+            Some((self.candidate - self.baseline) / self.baseline * 100.0)
+        }
+    }
+}
+
+/// Deltas for the whole run (`step` = "run") or a single step
+#[derive(Debug, Clone)]
+pub struct StepComparison {
+    pub step: String,
+    pub p50_ms: MetricDelta,
+    pub p95_ms: MetricDelta,
+    pub p99_ms: MetricDelta,
+    pub error_rate: MetricDelta,
+    pub rps: MetricDelta,
+    /// Probability of latencies at least this much slower by chance (Mann-Whitney U),
+    /// `None` when either run has too few successful samples
+    pub p_value: Option<f64>,
+    pub regressions: Vec<Regression>,
+}
+
+fn millis(duration: std::time::Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn delta(baseline: f64, candidate: f64) -> MetricDelta {
+    MetricDelta {
+        baseline,
+        candidate,
+    }
+}
+
+impl StepComparison {
+    fn from_steps(step: &str, baseline: &StepStats, candidate: &StepStats) -> Self {
+        Self {
+            step: step.to_string(),
+            p50_ms: delta(
+                millis(baseline.p50_response_time),
+                millis(candidate.p50_response_time),
+            ),
+            p95_ms: delta(
+                millis(baseline.p95_response_time),
+                millis(candidate.p95_response_time),
+            ),
+            p99_ms: delta(
+                millis(baseline.p99_response_time),
+                millis(candidate.p99_response_time),
+            ),
+            error_rate: delta(baseline.error_rate, candidate.error_rate),
+            rps: delta(baseline.requests_per_second, candidate.requests_per_second),
+            p_value: None,
+            regressions: Vec::new(),
+        }
+    }
+
+    fn from_runs(baseline: &LoadTestResults, candidate: &LoadTestResults) -> Self {
+        Self {
+            step: "run".to_string(),
+            p50_ms: delta(
+                millis(baseline.p50_response_time),
+                millis(candidate.p50_response_time),
+            ),
+            p95_ms: delta(
+                millis(baseline.p95_response_time),
+                millis(candidate.p95_response_time),
+            ),
+            p99_ms: delta(
+                millis(baseline.p99_response_time),
+                millis(candidate.p99_response_time),
+            ),
+            error_rate: delta(run_error_rate(baseline), run_error_rate(candidate)),
+            rps: delta(baseline.requests_per_second, candidate.requests_per_second),
+            p_value: None,
+            regressions: Vec::new(),
+        }
+    }
+
+    /// Flag every metric that moved the wrong way beyond the configured tolerance
+    fn detect_regressions(&mut self, config: &RegressionConfig) {
+        let significant = self.p_value.is_some_and(|p_value| p_value < config.alpha);
+        let mut flagged = Vec::new();
+
+        for (metric, value) in [
+            ("p50_ms", self.p50_ms),
+            ("p95_ms", self.p95_ms),
+            ("p99_ms", self.p99_ms),
+        ] {
+            if significant
+                && value
+                    .change_pct()
+                    .is_some_and(|change| change > config.tolerance_pct)
+            {
+                flagged.push((metric, value, self.p_value));
+            }
+        }
+        if self.error_rate.candidate - self.error_rate.baseline > config.max_error_rate_increase {
+            flagged.push(("error_rate", self.error_rate, None));
+        }
+        // Per-step throughput just follows the run's, so only the run is checked
+        if self.step == "run"
+            && self
+                .rps
+                .change_pct()
+                .is_some_and(|change| change < -config.tolerance_pct)
+        {
+            flagged.push(("rps", self.rps, None));
+        }
+
+        self.regressions = flagged
+            .into_iter()
+            .map(|(metric, value, p_value)| Regression {
+                scope: self.step.clone(),
+                metric: metric.to_string(),
+                baseline: value.baseline,
+                candidate: value.candidate,
+                p_value,
+            })
+            .collect();
+    }
+}
+
+fn run_error_rate(results: &LoadTestResults) -> f64 {
+    if results.total_requests > 0 {
+        100.0 - results.success_rate
+    } else {
+        0.0
+    }
+}
+
+/// Successful response times in milliseconds, for one step or the whole run
+fn latency_samples(report: &RunReport, step: Option<&str>) -> Vec<f64> {
+    report
+        .scenarios
+        .iter()
+        .flat_map(|scenario| &scenario.requests)
+        .filter(|request| request.success && step.is_none_or(|step| request.step == step))
+        .map(|request| millis(request.response_time))
+        .collect()
+}
+
+/// One-sided Mann-Whitney U test that `candidate` latencies are larger than
+/// `baseline` ones, using the tie-corrected normal approximation
+fn mann_whitney_p_value(baseline: &[f64], candidate: &[f64]) -> Option<f64> {
+    if baseline.len() < MIN_SAMPLES || candidate.len() < MIN_SAMPLES {
+        return None;
+    }
+
+    let mut combined: Vec<(f64, bool)> = baseline
+        .iter()
+        .map(|&value| (value, false))
+        .chain(candidate.iter().map(|&value| (value, true)))
+        .collect();
+    combined.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    // Average ranks across ties and collect the tie correction term
+    let n = combined.len() as f64;
+    let mut candidate_rank_sum = 0.0;
+    let mut tie_term = 0.0;
+    let mut start = 0;
+    while start < combined.len() {
+        let mut end = start;
+        while end + 1 < combined.len() && combined[end + 1].0 == combined[start].0 {
+            end += 1;
+        }
+        let tied = (end - start + 1) as f64;
+        let rank = (start + end) as f64 / 2.0 + 1.0;
+        candidate_rank_sum +=
+            rank * combined[start..=end].iter().filter(|(_, c)| *c).count() as f64;
+        tie_term += tied.powi(3) - tied;
+        start = end + 1;
+    }
+
+    let n1 = baseline.len() as f64;
+    let n2 = candidate.len() as f64;
+    let u = candidate_rank_sum - n2 * (n2 + 1.0) / 2.0;
+    let mean = n1 * n2 / 2.0;
+    let variance = n1 * n2 / 12.0 * ((n + 1.0) - tie_term / (n * (n - 1.0)));
+    if variance <= 0.0 {
+        // Every sample identical
+        return Some(1.0);
+    }
+
+    let z = (u - mean - 0.5) / variance.sqrt();
+    Some(0.5 * erfc(z / std::f64::consts::SQRT_2))
+}
+
+/// Complementary error function (Numerical Recipes erfcc, |error| < 1.2e-7)
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let result = t
+        * (-z * z - 1.26551223
+            + t * (1.00002368
+                + t * (0.37409196
+                    + t * (0.09678418
+                        + t * (-0.18628806
+                            + t * (0.27886807
+                                + t * (-1.13520398
+                                    + t * (1.48851587 + t * (-0.82215223 + t * 0.17087277)))))))))
+            .exp();
+    if x >= 0.0 {
+        result
+    } else {
+        2.0 - result
+    }
+}
+
+/// Compare the whole run, then every step present in both runs
+pub fn compare_runs(
+    baseline: &RunReport,
+    candidate: &RunReport,
+    config: &RegressionConfig,
+) -> Vec<StepComparison> {
+    let mut run = StepComparison::from_runs(&baseline.summary, &candidate.summary);
+    run.p_value = mann_whitney_p_value(
+        &latency_samples(baseline, None),
+        &latency_samples(candidate, None),
+    );
+    let mut comparisons = vec![run];
+
+    for candidate_step in &candidate.summary.steps {
+        if let Some(baseline_step) = baseline.summary.step(&candidate_step.step) {
+            let mut comparison =
+                StepComparison::from_steps(&candidate_step.step, baseline_step, candidate_step);
+            comparison.p_value = mann_whitney_p_value(
+                &latency_samples(baseline, Some(&candidate_step.step)),
+                &latency_samples(candidate, Some(&candidate_step.step)),
+            );
+            comparisons.push(comparison);
+        }
+    }
+
+    for comparison in &mut comparisons {
+        comparison.detect_regressions(config);
+    }
+    comparisons
+}
+
+/// Every regression across the run and its steps
+pub fn regressions(comparisons: &[StepComparison]) -> Vec<Regression> {
+    comparisons
+        .iter()
+        .flat_map(|comparison| comparison.regressions.iter().cloned())
+        .collect()
+}
+
+/// Format one metric as `base → candidate (+x%)`, red when it moved the wrong way
+fn format_delta(metric: &MetricDelta, unit: &str, higher_is_better: bool) -> ColoredString {
+    let change = metric.change_pct();
+    let text = match change {
+        Some(change) => format!(
+            "{:.1}{} → {:.1}{} ({:+.1}%)",
+            metric.baseline, unit, metric.candidate, unit, change
+        ),
+        None => format!(
+            "{:.1}{} → {:.1}{}",
+            metric.baseline, unit, metric.candidate, unit
+        ),
+    };
+
+    let worse = if higher_is_better {
+        metric.candidate < metric.baseline
+    } else {
+        metric.candidate > metric.baseline
+    };
+    if metric.candidate == metric.baseline {
+        text.normal()
+    } else if worse {
+        text.red()
+    } else {
+        text.green()
+    }
+}
+
+pub fn display_comparison(
+    baseline: &RunReport,
+    candidate: &RunReport,
+    comparisons: &[StepComparison],
+) {
+    println!("{}", "\n🔬 Run Comparison".blue().bold());
+    println!("{}", "═".repeat(50).purple());
+    println!(
+        "{}",
+        format!(
+            "Baseline:  {} ({} requests, started {})",
+            baseline.scenario, baseline.summary.total_requests, baseline.started_at
+        )
+        .bright_black()
+    );
+    println!(
+        "{}",
+        format!(
+            "Candidate: {} ({} requests, started {})",
+            candidate.scenario, candidate.summary.total_requests, candidate.started_at
+        )
+        .bright_black()
+    );
+
+    for comparison in comparisons {
+        println!("{}", format!("\n  {}", comparison.step).bold());
+        println!(
+            "    p50:    {}",
+            format_delta(&comparison.p50_ms, "ms", false)
+        );
+        println!(
+            "    p95:    {}",
+            format_delta(&comparison.p95_ms, "ms", false)
+        );
+        println!(
+            "    p99:    {}",
+            format_delta(&comparison.p99_ms, "ms", false)
+        );
+        println!(
+            "    errors: {}",
+            format_delta(&comparison.error_rate, "%", false)
+        );
+        println!("    rps:    {}", format_delta(&comparison.rps, "", true));
+        if let Some(p_value) = comparison.p_value {
+            println!(
+                "{}",
+                format!("    latency shift p-value: {:.4}", p_value).bright_black()
+            );
+        }
+        for regression in &comparison.regressions {
+            println!(
+                "{}",
+                format!(
+                    "    ✗ Regression in {}: {:.1} → {:.1}",
+                    regression.metric, regression.baseline, regression.candidate
+                )
+                .red()
+                .bold()
+            );
+        }
+    }
+
+    let missing: Vec<&str> = baseline
+        .summary
+        .steps
+        .iter()
+        .filter(|step| candidate.summary.step(&step.step).is_none())
+        .map(|step| step.step.as_str())
+        .collect();
+    if !missing.is_empty() {
+        println!(
+            "{}",
+            format!("\n⚠️  Steps missing from candidate: {}", missing.join(", ")).yellow()
+        );
+    }
+
+    let regressions = regressions(comparisons);
+    if regressions.is_empty() {
+        println!("{}", "\n✓ No regressions beyond tolerance".green());
+    } else {
+        println!(
+            "{}",
+            format!("\n❌ {} regression(s) beyond tolerance", regressions.len())
+                .red()
+                .bold()
+        );
+    }
+
+    println!("{}", format!("\n{}", "═".repeat(50)).purple());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASELINE: [f64; 10] = [10.0, 12.0, 11.0, 13.0, 12.0, 14.0, 10.0, 11.0, 12.0, 13.0];
+    const SLOWER: [f64; 10] = [13.0, 15.0, 14.0, 16.0, 12.0, 17.0, 14.0, 15.0, 13.0, 16.0];
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-6,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn slower_candidate_is_significant() {
+        // Ties across both samples, so this also covers the tie correction
+        let p = mann_whitney_p_value(&BASELINE, &SLOWER).unwrap();
+        assert_close(p, 0.001121347);
+    }
+
+    #[test]
+    fn faster_candidate_is_not_a_regression() {
+        let p = mann_whitney_p_value(&SLOWER, &BASELINE).unwrap();
+        assert_close(p, 0.999133252);
+    }
+
+    #[test]
+    fn interleaved_samples_are_not_significant() {
+        let baseline: Vec<f64> = (0..10).map(f64::from).collect();
+        let candidate: Vec<f64> = baseline.iter().map(|value| value + 0.5).collect();
+        let p = mann_whitney_p_value(&baseline, &candidate).unwrap();
+        assert_close(p, 0.366864998);
+    }
+
+    #[test]
+    fn identical_samples_give_p_of_one() {
+        assert_eq!(mann_whitney_p_value(&[5.0; 10], &[5.0; 10]), Some(1.0));
+    }
+
+    #[test]
+    fn too_few_samples_give_no_p_value() {
+        assert_eq!(mann_whitney_p_value(&BASELINE[..7], &SLOWER), None);
+        assert_eq!(mann_whitney_p_value(&BASELINE, &SLOWER[..7]), None);
+    }
+
+    fn comparison(p50: (f64, f64), p95: (f64, f64), p99: (f64, f64)) -> StepComparison {
+        StepComparison {
+            step: "run".to_string(),
+            p50_ms: delta(p50.0, p50.1),
+            p95_ms: delta(p95.0, p95.1),
+            p99_ms: delta(p99.0, p99.1),
+            error_rate: delta(0.0, 0.0),
+            rps: delta(100.0, 100.0),
+            p_value: Some(0.01),
+            regressions: Vec::new(),
+        }
+    }
+
+    fn flagged(comparison: &StepComparison) -> Vec<&str> {
+        comparison
+            .regressions
+            .iter()
+            .map(|regression| regression.metric.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn p99_regression_alone_is_flagged() {
+        let mut comparison = comparison((100.0, 101.0), (200.0, 205.0), (300.0, 450.0));
+        comparison.detect_regressions(&RegressionConfig::default());
+        assert_eq!(flagged(&comparison), vec!["p99_ms"]);
+    }
+
+    #[test]
+    fn latency_needs_significance_to_regress() {
+        let mut comparison = comparison((100.0, 200.0), (200.0, 400.0), (300.0, 600.0));
+        comparison.p_value = Some(0.2);
+        comparison.detect_regressions(&RegressionConfig::default());
+        assert!(flagged(&comparison).is_empty());
+
+        comparison.p_value = Some(0.01);
+        comparison.detect_regressions(&RegressionConfig::default());
+        assert_eq!(flagged(&comparison), vec!["p50_ms", "p95_ms", "p99_ms"]);
+    }
+
+    #[test]
+    fn error_rate_and_throughput_regress_without_a_p_value() {
+        let mut comparison = comparison((100.0, 100.0), (200.0, 200.0), (300.0, 300.0));
+        comparison.p_value = None;
+        comparison.error_rate = delta(0.5, 2.0);
+        comparison.rps = delta(100.0, 80.0);
+        comparison.detect_regressions(&RegressionConfig::default());
+        assert_eq!(flagged(&comparison), vec!["error_rate", "rps"]);
+    }
+
+    #[test]
+    fn erfc_matches_known_values() {
+        assert_close(erfc(0.0), 1.0);
+        assert_close(erfc(0.5), 0.479500122);
+        assert_close(erfc(-1.2), 1.910313978);
+    }
+}
@@ -0,0 +1,321 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::abort::AbortConfig;
+use crate::auth::AuthConfig;
+use crate::cleanup::CleanupConfig;
+use crate::compare::RegressionConfig;
+use crate::endpoint_provider::DiscoveryMode;
+use crate::feeders::FeederConfig;
+use crate::food_catalog::FoodCatalogConfig;
+use crate::payment::PaymentConfig;
+use crate::rate_limit::RateLimitConfig;
+use crate::report::OutputTarget;
+use crate::retry::RetryConfig;
+use crate::scenario::Scenario;
+use crate::session::ConnectionMode;
+use crate::ssm_discovery::DEFAULT_PATH_PREFIX;
+use crate::template::{self, Expr};
+use crate::thresholds::Thresholds;
+use crate::users::{is_id_safe, UserIdConfig};
+
+/// Fully resolved run configuration.
+///
+/// Values are layered, later layers winning: built-in defaults, the
+/// `--config` TOML file, `LOADTEST_*` environment variables, CLI flags.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RunConfig {
+    /// Number of concurrent users
+    pub users: usize,
+    /// Concurrent requests per user
+    pub concurrent: usize,
+    /// Ramp-up time in seconds (0 = no ramp-up)
+    pub rampup: u64,
+    pub region: String,
+    pub discovery: DiscoveryMode,
+    pub endpoints_file: Option<PathBuf>,
+    pub scenario: Option<PathBuf>,
+    /// Report outputs such as `json=results.json`
+    pub reporters: Vec<OutputTarget>,
+    /// Time-series window width in seconds; unset picks about 60 windows
+    pub window_secs: Option<u64>,
+    /// Load stages run one after another; empty means a single stage
+    /// built from `users`, `concurrent` and `rampup`
+    pub stages: Vec<Stage>,
+    /// Static endpoint overrides, applied above the discovery source
+    pub endpoints: BTreeMap<String, String>,
+    /// Test data files by name; the built-in PetSite feeders can be replaced
+    pub feeders: BTreeMap<String, FeederConfig>,
+    /// How the built-in scenario picks foods from the live catalog
+    pub food_catalog: FoodCatalogConfig,
+    /// Card and address data sent at checkout by the built-in scenario
+    pub payment: PaymentConfig,
+    /// Seed for user ids and every random choice; a run without one picks one
+    /// and records it here
+    pub seed: Option<u64>,
+    /// Tags this run's user ids so concurrent runs never share a user; a run
+    /// without one picks one and records it here
+    pub run_id: Option<String>,
+    pub user_ids: UserIdConfig,
+    pub ssm: SsmConfig,
+    pub http: HttpConfig,
+    /// Credentials by service name
+    pub auth: BTreeMap<String, AuthConfig>,
+    pub retry: RetryConfig,
+    pub rate_limit: RateLimitConfig,
+    /// Circuit breaker conditions that stop the run early
+    pub abort: AbortConfig,
+    pub cleanup: CleanupConfig,
+    pub thresholds: Thresholds,
+    /// Saved report to compare this run against
+    pub baseline: Option<PathBuf>,
+    pub regression: RegressionConfig,
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        Self {
+            users: 10,
+            concurrent: 5,
+            rampup: 0,
+            region: "us-east-1".to_string(),
+            discovery: DiscoveryMode::Ssm,
+            endpoints_file: None,
+            scenario: None,
+            reporters: Vec::new(),
+            window_secs: None,
+            stages: Vec::new(),
+            endpoints: BTreeMap::new(),
+            feeders: BTreeMap::new(),
+            food_catalog: FoodCatalogConfig::default(),
+            payment: PaymentConfig::default(),
+            seed: None,
+            run_id: None,
+            user_ids: UserIdConfig::default(),
+            ssm: SsmConfig::default(),
+            http: HttpConfig::default(),
+            auth: BTreeMap::new(),
+            retry: RetryConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+            abort: AbortConfig::default(),
+            cleanup: CleanupConfig::default(),
+            thresholds: Thresholds::default(),
+            baseline: None,
+            regression: RegressionConfig::default(),
+        }
+    }
+}
+
+/// One phase of the load profile
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Stage {
+    pub name: String,
+    pub users: usize,
+    pub concurrent: usize,
+    #[serde(default)]
+    pub rampup: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SsmConfig {
+    /// Prefix for relative parameter names
+    pub prefix: String,
+    /// Fetch everything under the prefix with GetParametersByPath
+    pub by_path: bool,
+    /// Warn and use env vars and defaults when SSM can't be read, instead of failing
+    pub fallback: bool,
+    /// Service name -> parameter name, merged over the built-in mappings
+    pub parameters: BTreeMap<String, String>,
+}
+
+impl Default for SsmConfig {
+    fn default() -> Self {
+        Self {
+            prefix: DEFAULT_PATH_PREFIX.to_string(),
+            by_path: false,
+            fallback: false,
+            parameters: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    /// Whole-request timeout in seconds
+    pub timeout_secs: u64,
+    /// TCP connect timeout in seconds
+    pub connect_timeout_secs: u64,
+    /// Sent with every request; values are templates that can use the built-in functions
+    pub headers: BTreeMap<String, String>,
+    /// Give each virtual user its own cookie jar
+    pub cookies: bool,
+    /// How virtual users get connections: one shared pool, a pool each, or a new one per request
+    pub connections: ConnectionMode,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: 10,
+            connect_timeout_secs: 5,
+            headers: BTreeMap::from([(
+                "User-Agent".to_string(),
+                "LoadTester-{{user_id}}".to_string(),
+            )]),
+            cookies: true,
+            connections: ConnectionMode::default(),
+        }
+    }
+}
+
+impl HttpConfig {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.timeout_secs == 0 {
+            anyhow::bail!("http.timeout_secs must be greater than 0");
+        }
+        for (name, value) in &self.headers {
+            reqwest::header::HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| anyhow::anyhow!("http.headers: '{}' is not a header name", name))?;
+            let expressions = template::expressions(value)
+                .map_err(|err| anyhow::anyhow!("http.headers.{}: {}", name, err))?;
+            // Feeder rows and captures belong to one scenario; these headers go on every request
+            if expressions
+                .iter()
+                .any(|expr| matches!(expr, Expr::Feeder(..) | Expr::Var(_)))
+            {
+                anyhow::bail!(
+                    "http.headers.{} can only use the functions {}",
+                    name,
+                    template::FUNCTIONS.join(", ")
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse a `--header` value such as `X-Api-Version: 2`
+pub fn parse_header(arg: &str) -> Result<(String, String), String> {
+    let (name, value) = arg
+        .split_once(':')
+        .ok_or_else(|| format!("expected NAME: VALUE, got '{}'", arg))?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(format!("missing header name in '{}'", arg));
+    }
+    Ok((name.to_string(), value.trim().to_string()))
+}
+
+impl RunConfig {
+    /// Read a config file, or return the defaults when no file is given
+    pub fn load(path: Option<&Path>) -> anyhow::Result<Self> {
+        let Some(path) = path else {
+            return Ok(Self::default());
+        };
+        let contents = std::fs::read_to_string(path)
+            .map_err(|err| anyhow::anyhow!("Failed to read {}: {}", path.display(), err))?;
+        toml::from_str(&contents)
+            .map_err(|err| anyhow::anyhow!("Invalid config {}: {}", path.display(), err))
+    }
+
+    /// The stages to run, falling back to a single stage from the top-level settings
+    pub fn effective_stages(&self) -> Vec<Stage> {
+        if self.stages.is_empty() {
+            vec![Stage {
+                name: "main".to_string(),
+                users: self.users,
+                concurrent: self.concurrent,
+                rampup: self.rampup,
+            }]
+        } else {
+            self.stages.clone()
+        }
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for stage in self.effective_stages() {
+            if stage.users == 0 || stage.concurrent == 0 {
+                anyhow::bail!(
+                    "Stage '{}' needs at least one user and one concurrent request",
+                    stage.name
+                );
+            }
+        }
+        if self.seed.is_some_and(|seed| seed > i64::MAX as u64) {
+            anyhow::bail!("seed must be at most {}", i64::MAX);
+        }
+        self.http.validate()?;
+        for (service, auth) in &self.auth {
+            auth.validate(service)?;
+        }
+        self.retry.validate()?;
+        self.rate_limit.validate()?;
+        self.cleanup.validate()?;
+        self.food_catalog.validate()?;
+        self.payment.validate()?;
+        self.user_ids.validate(
+            self.effective_stages()
+                .iter()
+                .map(|stage| stage.users)
+                .sum(),
+        )?;
+        if let Some(run_id) = &self.run_id {
+            if run_id.is_empty() || !is_id_safe(run_id) {
+                anyhow::bail!("run_id may only contain letters, digits, '-' and '_'");
+            }
+        }
+        if self.window_secs == Some(0) {
+            anyhow::bail!("window_secs must be greater than 0");
+        }
+        if !(0.0..=1.0).contains(&self.regression.alpha) {
+            anyhow::bail!("regression.alpha must be between 0 and 1");
+        }
+        Ok(())
+    }
+
+    /// Whether SSM discovery or SigV4 signing needs the AWS SDK config
+    pub fn needs_aws(&self) -> bool {
+        self.discovery == DiscoveryMode::Ssm || self.auth.values().any(AuthConfig::needs_aws)
+    }
+
+    /// Per-step thresholds and retry policies must name steps of the scenario
+    pub fn check_steps(&self, scenario: &Scenario) -> anyhow::Result<()> {
+        let step_names = scenario.step_names();
+        for (table, steps) in [
+            (
+                "Thresholds",
+                self.thresholds.steps.keys().collect::<Vec<_>>(),
+            ),
+            ("Retry policies", self.retry.steps.keys().collect()),
+        ] {
+            let unknown: Vec<&str> = steps
+                .into_iter()
+                .map(String::as_str)
+                .filter(|step| !step_names.contains(step))
+                .collect();
+            if !unknown.is_empty() {
+                anyhow::bail!(
+                    "{} reference steps not in scenario '{}': {}",
+                    table,
+                    scenario.name(),
+                    unknown.join(", ")
+                );
+            }
+        }
+        Ok(())
+    }
+
+    pub fn window(&self) -> Option<Duration> {
+        self.window_secs.map(Duration::from_secs)
+    }
+
+    pub fn to_toml(&self) -> anyhow::Result<String> {
+        Ok(toml::to_string_pretty(self)?)
+    }
+}
@@ -0,0 +1,208 @@
+use async_trait::async_trait;
+use clap::ValueEnum;
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+use crate::types::Endpoints;
+
+/// A source of service endpoints, keyed by service name (e.g. `petsearch`).
+#[async_trait]
+pub trait EndpointProvider: Send + Sync {
+    /// Short name shown when reporting where an endpoint came from
+    fn name(&self) -> &str;
+
+    /// Return every endpoint this provider knows about
+    async fn discover(&self) -> anyhow::Result<HashMap<String, String>>;
+}
+
+/// Which provider supplies endpoints before CLI overrides are applied
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiscoveryMode {
+    /// AWS SSM Parameter Store (requires AWS credentials)
+    Ssm,
+    /// `*_ENDPOINT` environment variables only
+    Env,
+    /// Static TOML file passed with `--endpoints-file`
+    File,
+}
+
+/// Reads the `*_ENDPOINT` environment variables
+pub struct EnvEndpointProvider;
+
+impl EnvEndpointProvider {
+    /// Service name -> environment variable holding its URL
+    pub const VARIABLES: [(&'static str, &'static str); 4] = [
+        ("petlistadoptions", "PETLIST_ENDPOINT"),
+        ("petsearch", "PETSEARCH_ENDPOINT"),
+        ("payforadoption", "PAYFORADOPTION_ENDPOINT"),
+        ("petfood", "PETFOOD_ENDPOINT"),
+    ];
+}
+
+#[async_trait]
+impl EndpointProvider for EnvEndpointProvider {
+    fn name(&self) -> &str {
+        "env"
+    }
+
+    async fn discover(&self) -> anyhow::Result<HashMap<String, String>> {
+        Ok(Self::VARIABLES
+            .iter()
+            .filter_map(|(service, variable)| {
+                std::env::var(variable)
+                    .ok()
+                    .filter(|value| !value.is_empty())
+                    .map(|value| (service.to_string(), value))
+            })
+            .collect())
+    }
+}
+
+/// Reads endpoints from the `[endpoints]` table of a TOML file:
+///
+/// ```toml
+/// [endpoints]
+/// petsearch = "http://localhost:8081/api/search"
+/// ```
+pub struct FileEndpointProvider {
+    path: PathBuf,
+}
+
+impl FileEndpointProvider {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[derive(Deserialize)]
+struct EndpointsFile {
+    #[serde(default)]
+    endpoints: HashMap<String, String>,
+}
+
+#[async_trait]
+impl EndpointProvider for FileEndpointProvider {
+    fn name(&self) -> &str {
+        "file"
+    }
+
+    async fn discover(&self) -> anyhow::Result<HashMap<String, String>> {
+        let contents = tokio::fs::read_to_string(&self.path)
+            .await
+            .map_err(|err| anyhow::anyhow!("Failed to read {}: {}", self.path.display(), err))?;
+        let file: EndpointsFile = toml::from_str(&contents).map_err(|err| {
+            anyhow::anyhow!("Invalid endpoints file {}: {}", self.path.display(), err)
+        })?;
+        Ok(file.endpoints)
+    }
+}
+
+/// The resolved config's `[endpoints]` table, with `--endpoint service=url` overrides applied
+pub struct ConfigEndpointProvider {
+    endpoints: BTreeMap<String, String>,
+}
+
+impl ConfigEndpointProvider {
+    pub fn new(endpoints: BTreeMap<String, String>) -> Self {
+        Self { endpoints }
+    }
+}
+
+#[async_trait]
+impl EndpointProvider for ConfigEndpointProvider {
+    fn name(&self) -> &str {
+        "config"
+    }
+
+    async fn discover(&self) -> anyhow::Result<HashMap<String, String>> {
+        Ok(self.endpoints.clone().into_iter().collect())
+    }
+}
+
+/// Parse a `key=value` command line argument
+pub fn parse_key_value(arg: &str) -> Result<(String, String), String> {
+    match arg
+        .split_once('=')
+        .map(|(key, value)| (key.trim(), value.trim()))
+    {
+        Some((key, value)) if !key.is_empty() && !value.is_empty() => {
+            Ok((key.to_string(), value.to_string()))
+        }
+        _ => Err(format!("expected <name>=<value>, got '{}'", arg)),
+    }
+}
+
+/// Resolve endpoints from providers listed in increasing order of precedence.
+///
+/// Every service starts from the built-in localhost default and is replaced
+/// by each provider that knows about it, so the last provider wins.
+pub async fn resolve_endpoints(
+    providers: &[Box<dyn EndpointProvider>],
+) -> anyhow::Result<Endpoints> {
+    let mut endpoints = Endpoints::new();
+    let mut sources: HashMap<String, String> = HashMap::new();
+
+    for provider in providers {
+        for (service, url) in provider.discover().await? {
+            match endpoints.set(&service, url) {
+                Ok(()) => {
+                    sources.insert(service, provider.name().to_string());
+                }
+                Err(err) => println!(
+                    "{}",
+                    format!("⚠️  Ignoring endpoint from {}: {}", provider.name(), err).yellow()
+                ),
+            }
+        }
+    }
+
+    for (service, url) in endpoints.iter() {
+        match sources.get(service) {
+            Some(source) => println!(
+                "{}",
+                format!("✓ Using {}: {} ({})", service, url, source).green()
+            ),
+            None => println!(
+                "{}",
+                format!("🔄 Using fallback for {}: {}", service, url).cyan()
+            ),
+        }
+    }
+
+    Ok(endpoints)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_trims_key_value() {
+        assert_eq!(
+            parse_key_value(" petsearch = http://localhost:8081 "),
+            Ok(("petsearch".to_string(), "http://localhost:8081".to_string()))
+        );
+        // Only the first `=` splits, so values may contain more
+        assert_eq!(
+            parse_key_value("petfood=http://x/?a=b"),
+            Ok(("petfood".to_string(), "http://x/?a=b".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_empty_or_blank_parts() {
+        for arg in [
+            "",
+            "petsearch",
+            "=http://x",
+            " =http://x",
+            "petsearch=",
+            "petsearch= ",
+        ] {
+            assert!(parse_key_value(arg).is_err(), "'{}' parsed", arg);
+        }
+    }
+}
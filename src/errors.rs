@@ -0,0 +1,273 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use tokio_native_tls::native_tls;
+
+use crate::types::RequestResult;
+
+/// Why a request failed, saved as a short key such as `connect_refused` or `http_503`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum ErrorKind {
+    Dns,
+    ConnectRefused,
+    ConnectTimeout,
+    /// Any other failure to establish a connection
+    Connect,
+    Tls,
+    /// Credentials for the request could not be obtained, e.g. an OAuth2 token
+    Auth,
+    RequestTimeout,
+    /// A response outside 2xx/3xx (or the step's `expect_status`)
+    Http(u16),
+    BodyRead,
+    /// The response arrived but did not match what the step expected
+    Assertion,
+    /// Not sent because a variable it uses was never captured
+    Skipped,
+    Other,
+}
+
+impl ErrorKind {
+    pub fn key(&self) -> String {
+        match self {
+            Self::Dns => "dns".to_string(),
+            Self::ConnectRefused => "connect_refused".to_string(),
+            Self::ConnectTimeout => "connect_timeout".to_string(),
+            Self::Connect => "connect".to_string(),
+            Self::Tls => "tls".to_string(),
+            Self::Auth => "auth".to_string(),
+            Self::RequestTimeout => "request_timeout".to_string(),
+            Self::Http(status) => format!("http_{}", status),
+            Self::BodyRead => "body_read".to_string(),
+            Self::Assertion => "assertion".to_string(),
+            Self::Skipped => "skipped".to_string(),
+            Self::Other => "other".to_string(),
+        }
+    }
+
+    /// Classify a transport error by walking its source chain
+    pub fn from_reqwest(err: &reqwest::Error) -> Self {
+        if err.is_timeout() {
+            return if err.is_connect() {
+                Self::ConnectTimeout
+            } else {
+                Self::RequestTimeout
+            };
+        }
+        if err.is_body() || err.is_decode() {
+            return Self::BodyRead;
+        }
+
+        let mut source = err.source();
+        while let Some(cause) = source {
+            if cause.downcast_ref::<native_tls::Error>().is_some() {
+                return Self::Tls;
+            }
+            if let Some(io) = cause.downcast_ref::<std::io::Error>() {
+                match io.kind() {
+                    std::io::ErrorKind::ConnectionRefused => return Self::ConnectRefused,
+                    std::io::ErrorKind::TimedOut => return Self::ConnectTimeout,
+                    _ => {}
+                }
+            }
+            // hyper and the TLS backend only expose some causes as messages
+            let message = cause.to_string().to_lowercase();
+            if message.contains("dns error") || message.contains("failed to lookup address") {
+                return Self::Dns;
+            }
+            if message.contains("certificate") || message.contains("ssl") || message.contains("tls")
+            {
+                return Self::Tls;
+            }
+            source = cause.source();
+        }
+
+        if err.is_connect() {
+            Self::Connect
+        } else {
+            Self::Other
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Dns => write!(f, "DNS"),
+            Self::ConnectRefused => write!(f, "connect refused"),
+            Self::ConnectTimeout => write!(f, "connect timeout"),
+            Self::Connect => write!(f, "connect error"),
+            Self::Tls => write!(f, "TLS"),
+            Self::Auth => write!(f, "auth error"),
+            Self::RequestTimeout => write!(f, "request timeout"),
+            Self::Http(status) => write!(f, "HTTP {}", status),
+            Self::BodyRead => write!(f, "body read error"),
+            Self::Assertion => write!(f, "assertion failure"),
+            Self::Skipped => write!(f, "skipped step"),
+            Self::Other => write!(f, "other"),
+        }
+    }
+}
+
+impl FromStr for ErrorKind {
+    type Err = String;
+
+    fn from_str(key: &str) -> Result<Self, Self::Err> {
+        match key {
+            "dns" => Ok(Self::Dns),
+            "connect_refused" => Ok(Self::ConnectRefused),
+            "connect_timeout" => Ok(Self::ConnectTimeout),
+            "connect" => Ok(Self::Connect),
+            "tls" => Ok(Self::Tls),
+            "auth" => Ok(Self::Auth),
+            "request_timeout" => Ok(Self::RequestTimeout),
+            "body_read" => Ok(Self::BodyRead),
+            "assertion" => Ok(Self::Assertion),
+            "skipped" => Ok(Self::Skipped),
+            "other" => Ok(Self::Other),
+            other => other
+                .strip_prefix("http_")
+                .and_then(|status| status.parse().ok())
+                .map(Self::Http)
+                .ok_or_else(|| format!("unknown error kind '{}'", other)),
+        }
+    }
+}
+
+impl TryFrom<String> for ErrorKind {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<ErrorKind> for String {
+    fn from(kind: ErrorKind) -> Self {
+        kind.key()
+    }
+}
+
+/// Failures of one kind across the run
+#[derive(Debug, Clone)]
+pub struct ErrorGroup {
+    pub kind: ErrorKind,
+    pub count: usize,
+    /// Steps that hit this error, in first-seen order
+    pub steps: Vec<String>,
+    pub sample_url: String,
+    pub sample_message: Option<String>,
+}
+
+/// Group failed requests by kind, most frequent first
+pub fn group_errors<'a>(requests: impl IntoIterator<Item = &'a RequestResult>) -> Vec<ErrorGroup> {
+    let mut groups: Vec<ErrorGroup> = Vec::new();
+    for request in requests {
+        let Some(kind) = request.failure_kind() else {
+            continue;
+        };
+        match groups.iter_mut().find(|group| group.kind == kind) {
+            Some(group) => {
+                group.count += 1;
+                if !group.steps.contains(&request.step) {
+                    group.steps.push(request.step.clone());
+                }
+            }
+            None => groups.push(ErrorGroup {
+                kind,
+                count: 1,
+                steps: vec![request.step.clone()],
+                sample_url: request.url.clone(),
+                sample_message: request.error.clone(),
+            }),
+        }
+    }
+    groups.sort_by_key(|group| std::cmp::Reverse(group.count));
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+
+    async fn classify(url: &str, timeout: Duration) -> ErrorKind {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .no_proxy()
+            .build()
+            .unwrap();
+        let err = client.get(url).send().await.unwrap_err();
+        ErrorKind::from_reqwest(&err)
+    }
+
+    #[test]
+    fn keys_round_trip() {
+        for kind in [
+            ErrorKind::Dns,
+            ErrorKind::ConnectRefused,
+            ErrorKind::RequestTimeout,
+            ErrorKind::Http(503),
+            ErrorKind::Other,
+        ] {
+            assert_eq!(kind.key().parse::<ErrorKind>().unwrap(), kind);
+        }
+        assert!("http_teapot".parse::<ErrorKind>().is_err());
+        assert!("refused".parse::<ErrorKind>().is_err());
+    }
+
+    #[tokio::test]
+    async fn classifies_a_refused_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        drop(listener);
+
+        let url = format!("http://{}/", address);
+        let kind = classify(&url, Duration::from_secs(5)).await;
+        assert_eq!(kind, ErrorKind::ConnectRefused);
+    }
+
+    #[tokio::test]
+    async fn classifies_a_server_that_never_answers_as_a_request_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let accept = tokio::spawn(async move { listener.accept().await });
+
+        let kind = classify(&url, Duration::from_millis(200)).await;
+        assert_eq!(kind, ErrorKind::RequestTimeout);
+        accept.abort();
+    }
+
+    fn request(step: &str, status: u16, error: Option<&str>) -> RequestResult {
+        RequestResult {
+            step: step.to_string(),
+            status,
+            error: error.map(str::to_string),
+            ..RequestResult::default()
+        }
+    }
+
+    #[test]
+    fn groups_failures_by_kind_most_frequent_first() {
+        let requests = [
+            request("search", 503, None),
+            request("adopt", 0, Some("Request timeout")),
+            request("adopt", 503, None),
+            RequestResult {
+                success: true,
+                ..request("search", 200, None)
+            },
+            request("search", 503, None),
+        ];
+        let groups = group_errors(&requests);
+        let kinds: Vec<(ErrorKind, usize)> = groups.iter().map(|g| (g.kind, g.count)).collect();
+        assert_eq!(
+            kinds,
+            [(ErrorKind::Http(503), 3), (ErrorKind::RequestTimeout, 1)]
+        );
+        assert_eq!(groups[0].steps, ["search", "adopt"]);
+    }
+}
@@ -0,0 +1,382 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::config::RunConfig;
+use crate::scenario::Scenario;
+
+/// Feeders the built-in PetSite scenario draws from, with the column it reads
+/// and the values used when no file replaces them
+pub const PETSITE_FEEDERS: [(&str, &str, &[&str]); 5] = [
+    // Includes colors the pet search does not know
+    (
+        "colors",
+        "color",
+        &["black", "brown", "purple", "red", "blue"],
+    ),
+    ("pet_types", "pet_type", &["puppy", "kitten", "bunny"]),
+    ("max_prices", "max_price", &["10", "25", "50", "100"]),
+    (
+        "search_terms",
+        "term",
+        &["royal", "premium", "organic", "chicken"],
+    ),
+    (
+        "food_ids",
+        "food_id",
+        &[
+            "F046a4eca",
+            "Fecd30d31",
+            "F36a222eb",
+            "Fc7f447a1",
+            "F233c473c",
+            "Ffb5ef0e2",
+        ],
+    ),
+];
+
+/// Which row each draw takes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeederStrategy {
+    /// Rows in file order, each used once; no new scenarios start once they run out
+    Sequential,
+    /// Any row, every draw
+    #[default]
+    Random,
+    /// One row per virtual user, never shared; no new scenarios start once they run out
+    Unique,
+    /// Rows in file order, starting over after the last one
+    Circular,
+}
+
+/// A data file scenarios draw rows from:
+///
+/// ```toml
+/// [feeders.search_terms]
+/// file = "data/search-terms.csv"
+/// strategy = "circular"
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FeederConfig {
+    /// CSV with a header row, or JSON: an array of objects or of plain values
+    /// (read as the column `value`)
+    pub file: PathBuf,
+    #[serde(default)]
+    pub strategy: FeederStrategy,
+}
+
+/// Parse a `--feeder` value such as `search_terms=data/terms.csv`
+pub fn parse_feeder(arg: &str) -> Result<(String, FeederConfig), String> {
+    let (name, file) = crate::endpoint_provider::parse_key_value(arg)?;
+    Ok((
+        name,
+        FeederConfig {
+            file: PathBuf::from(file),
+            strategy: FeederStrategy::default(),
+        },
+    ))
+}
+
+pub type Record = HashMap<String, String>;
+
+#[derive(Default)]
+struct FeederState {
+    /// Next row for sequential and circular draws
+    cursor: usize,
+    /// Row pinned to each user by the unique strategy
+    users: HashMap<String, usize>,
+}
+
+pub struct Feeder {
+    strategy: FeederStrategy,
+    columns: Vec<String>,
+    rows: Vec<Record>,
+    state: Mutex<FeederState>,
+}
+
+impl Feeder {
+    fn new(strategy: FeederStrategy, columns: Vec<String>, rows: Vec<Record>) -> Self {
+        Self {
+            strategy,
+            columns,
+            rows,
+            state: Mutex::new(FeederState::default()),
+        }
+    }
+
+    fn builtin(column: &str, values: &[&str]) -> Self {
+        Self::new(
+            FeederStrategy::Random,
+            vec![column.to_string()],
+            values
+                .iter()
+                .map(|value| Record::from([(column.to_string(), value.to_string())]))
+                .collect(),
+        )
+    }
+
+    fn load(path: &Path, strategy: FeederStrategy) -> anyhow::Result<Self> {
+        let is_json = path
+            .extension()
+            .is_some_and(|extension| extension == "json");
+        let (columns, rows) = if is_json {
+            read_json(path)?
+        } else {
+            read_csv(path)?
+        };
+        if rows.is_empty() {
+            anyhow::bail!("Feeder file {} has no rows", path.display());
+        }
+        Ok(Self::new(strategy, columns, rows))
+    }
+
+    /// Take a row for `user_id`; the flag is set when a sequential or unique
+    /// feeder has run out and the row is being reused
+    fn draw(&self, user_id: &str, rng: &mut impl Rng) -> (&Record, bool) {
+        let mut state = self.state.lock().unwrap();
+        let (index, ran_out) = match self.strategy {
+            FeederStrategy::Random => (rng.gen_range(0..self.rows.len()), false),
+            FeederStrategy::Sequential | FeederStrategy::Circular => {
+                let cursor = state.cursor;
+                state.cursor += 1;
+                (
+                    cursor % self.rows.len(),
+                    self.strategy == FeederStrategy::Sequential && cursor >= self.rows.len(),
+                )
+            }
+            FeederStrategy::Unique => {
+                let next = state.users.len();
+                let index = *state.users.entry(user_id.to_string()).or_insert(next);
+                (index % self.rows.len(), index >= self.rows.len())
+            }
+        };
+        (&self.rows[index], ran_out)
+    }
+}
+
+fn read_csv(path: &Path) -> anyhow::Result<(Vec<String>, Vec<Record>)> {
+    let mut reader = csv::Reader::from_path(path)
+        .map_err(|err| anyhow::anyhow!("Failed to read {}: {}", path.display(), err))?;
+    let columns: Vec<String> = reader
+        .headers()
+        .map_err(|err| anyhow::anyhow!("Invalid CSV {}: {}", path.display(), err))?
+        .iter()
+        .map(|column| column.trim().to_string())
+        .collect();
+
+    let mut rows = Vec::new();
+    for row in reader.records() {
+        let row = row.map_err(|err| anyhow::anyhow!("Invalid CSV {}: {}", path.display(), err))?;
+        rows.push(
+            columns
+                .iter()
+                .cloned()
+                .zip(row.iter().map(|value| value.trim().to_string()))
+                .collect(),
+        );
+    }
+    Ok((columns, rows))
+}
+
+fn read_json(path: &Path) -> anyhow::Result<(Vec<String>, Vec<Record>)> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|err| anyhow::anyhow!("Failed to read {}: {}", path.display(), err))?;
+    let values: Vec<serde_json::Value> = serde_json::from_str(&contents).map_err(|err| {
+        anyhow::anyhow!(
+            "Invalid feeder {}: {} (expected a JSON array)",
+            path.display(),
+            err
+        )
+    })?;
+
+    let text = |value: &serde_json::Value| match value {
+        serde_json::Value::String(text) => text.clone(),
+        other => other.to_string(),
+    };
+    let mut columns: Vec<String> = Vec::new();
+    let rows = values
+        .iter()
+        .map(|value| {
+            let record: Record = match value {
+                serde_json::Value::Object(fields) => fields
+                    .iter()
+                    .map(|(column, value)| (column.clone(), text(value)))
+                    .collect(),
+                other => Record::from([("value".to_string(), text(other))]),
+            };
+            for column in record.keys() {
+                if !columns.contains(column) {
+                    columns.push(column.clone());
+                }
+            }
+            record
+        })
+        .collect();
+    Ok((columns, rows))
+}
+
+/// Every feeder available to the scenario, by name
+pub struct Feeders {
+    feeders: BTreeMap<String, Feeder>,
+}
+
+impl Feeders {
+    /// The built-in PetSite feeders, replaced or extended by the configured files,
+    /// checked against every `{{feeder.column}}` the scenario uses
+    pub fn load(config: &RunConfig, scenario: &Scenario) -> anyhow::Result<Self> {
+        let total_users: usize = config
+            .effective_stages()
+            .iter()
+            .map(|stage| stage.users)
+            .sum();
+        let mut feeders: BTreeMap<String, Feeder> = PETSITE_FEEDERS
+            .iter()
+            .map(|(name, column, values)| (name.to_string(), Feeder::builtin(column, values)))
+            .collect();
+
+        for (name, feeder_config) in &config.feeders {
+            let feeder = Feeder::load(&feeder_config.file, feeder_config.strategy)
+                .map_err(|err| anyhow::anyhow!("Feeder '{}': {}", name, err))?;
+            if feeder.strategy == FeederStrategy::Unique && feeder.rows.len() < total_users {
+                anyhow::bail!(
+                    "Feeder '{}' has {} rows but the run has {} users, and the unique strategy needs one row per user",
+                    name,
+                    feeder.rows.len(),
+                    total_users
+                );
+            }
+            if let Some((_, column, _)) = PETSITE_FEEDERS
+                .iter()
+                .find(|(builtin, _, _)| builtin == name)
+            {
+                if !feeder.columns.iter().any(|existing| existing == column) {
+                    anyhow::bail!(
+                        "Feeder '{}' replaces a built-in feeder and needs a '{}' column",
+                        name,
+                        column
+                    );
+                }
+            }
+            feeders.insert(name.clone(), feeder);
+        }
+
+        let feeders = Self { feeders };
+        feeders.check(&scenario.feeder_references())?;
+        Ok(feeders)
+    }
+
+    /// Fail unless each `(feeder, column)` reference names an existing feeder column
+    fn check(&self, references: &[(String, String)]) -> anyhow::Result<()> {
+        for (name, column) in references {
+            let feeder = self
+                .feeders
+                .get(name)
+                .ok_or_else(|| anyhow::anyhow!("Scenario references unknown feeder '{}'", name))?;
+            if !feeder.columns.contains(column) {
+                anyhow::bail!(
+                    "Feeder '{}' has no column '{}' (columns: {})",
+                    name,
+                    column,
+                    feeder.columns.join(", ")
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Draw a row from feeder `name`; see `Feeder::draw`
+    pub fn draw(&self, name: &str, user_id: &str, rng: &mut impl Rng) -> Option<(&Record, bool)> {
+        self.feeders
+            .get(name)
+            .map(|feeder| feeder.draw(user_id, rng))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn feeder(strategy: FeederStrategy) -> Feeder {
+        let mut feeder = Feeder::builtin("term", &["a", "b"]);
+        feeder.strategy = strategy;
+        feeder
+    }
+
+    /// The value each listed user draws in turn, with the ran-out flag
+    fn draws(feeder: &Feeder, users: &[&str]) -> Vec<(String, bool)> {
+        let mut rng = StdRng::seed_from_u64(1);
+        users
+            .iter()
+            .map(|user| {
+                let (record, ran_out) = feeder.draw(user, &mut rng);
+                (record["term"].clone(), ran_out)
+            })
+            .collect()
+    }
+
+    fn pairs(expected: &[(&str, bool)]) -> Vec<(String, bool)> {
+        expected
+            .iter()
+            .map(|(value, ran_out)| (value.to_string(), *ran_out))
+            .collect()
+    }
+
+    #[test]
+    fn sequential_flags_rows_reused_after_running_out() {
+        let draws = draws(&feeder(FeederStrategy::Sequential), &["u1", "u1", "u2"]);
+        assert_eq!(draws, pairs(&[("a", false), ("b", false), ("a", true)]));
+    }
+
+    #[test]
+    fn circular_starts_over_without_running_out() {
+        let draws = draws(&feeder(FeederStrategy::Circular), &["u1", "u1", "u2"]);
+        assert_eq!(draws, pairs(&[("a", false), ("b", false), ("a", false)]));
+    }
+
+    #[test]
+    fn unique_pins_one_row_per_user() {
+        let draws = draws(&feeder(FeederStrategy::Unique), &["u1", "u2", "u1", "u3"]);
+        assert_eq!(
+            draws,
+            pairs(&[("a", false), ("b", false), ("a", false), ("a", true)])
+        );
+    }
+
+    #[test]
+    fn reads_csv_and_json_rows() {
+        let dir = std::env::temp_dir().join(format!("feeders-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let csv = dir.join("terms.csv");
+        std::fs::write(&csv, "term, limit\nroyal, 5\norganic,10\n").unwrap();
+        let json = dir.join("terms.json");
+        std::fs::write(&json, r#"["royal", {"term": "organic", "limit": 10}]"#).unwrap();
+
+        let (columns, rows) = read_csv(&csv).unwrap();
+        assert_eq!(columns, ["term", "limit"]);
+        assert_eq!(rows[0]["limit"], "5");
+        assert_eq!(rows[1]["term"], "organic");
+
+        let (columns, rows) = read_json(&json).unwrap();
+        assert_eq!(columns.len(), 3);
+        assert_eq!(rows[0]["value"], "royal");
+        assert_eq!(rows[1]["limit"], "10");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn references_must_name_a_feeder_column() {
+        let feeders = Feeders {
+            feeders: BTreeMap::from([("terms".to_string(), feeder(FeederStrategy::Random))]),
+        };
+        let reference = |name: &str, column: &str| vec![(name.to_string(), column.to_string())];
+        assert!(feeders.check(&reference("terms", "term")).is_ok());
+        assert!(feeders.check(&reference("terms", "color")).is_err());
+        assert!(feeders.check(&reference("colors", "color")).is_err());
+    }
+}
@@ -0,0 +1,203 @@
+use colored::*;
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// How the built-in scenario picks a food from the live catalog:
+///
+/// ```toml
+/// [food_catalog]
+/// weighting = "price"
+/// category_weights = { dry = 3.0, treats = 0.5 }
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FoodCatalogConfig {
+    pub weighting: FoodWeighting,
+    /// Multiplies the weight of foods in a category; unlisted categories keep 1
+    pub category_weights: BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FoodWeighting {
+    /// Every food equally likely
+    #[default]
+    Uniform,
+    /// Pricier foods more likely
+    Price,
+    /// Cheaper foods more likely
+    InversePrice,
+}
+
+impl FoodCatalogConfig {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for (category, weight) in &self.category_weights {
+            if !weight.is_finite() || *weight < 0.0 {
+                anyhow::bail!(
+                    "food_catalog.category_weights.{} must be 0 or more",
+                    category
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Pick one food; falls back to an even pick when no food has any weight
+    pub fn pick<'a>(&self, foods: &'a [Food], rng: &mut impl Rng) -> Option<&'a Food> {
+        if foods.is_empty() {
+            return None;
+        }
+
+        let prices: Vec<f64> = foods
+            .iter()
+            .filter_map(|food| food.price)
+            .filter(|price| *price > 0.0)
+            .collect();
+        // Foods without a usable price weigh as much as an average one
+        let average_price = if prices.is_empty() {
+            1.0
+        } else {
+            prices.iter().sum::<f64>() / prices.len() as f64
+        };
+
+        let weights = foods.iter().map(|food| {
+            let price = food
+                .price
+                .filter(|price| *price > 0.0)
+                .unwrap_or(average_price);
+            let weight = match self.weighting {
+                FoodWeighting::Uniform => 1.0,
+                FoodWeighting::Price => price,
+                FoodWeighting::InversePrice => 1.0 / price,
+            };
+            let category = food
+                .category
+                .as_ref()
+                .and_then(|category| self.category_weights.get(category))
+                .copied()
+                .unwrap_or(1.0);
+            weight * category
+        });
+
+        match WeightedIndex::new(weights) {
+            Ok(index) => foods.get(index.sample(rng)),
+            Err(_) => foods.get(rng.gen_range(0..foods.len())),
+        }
+    }
+}
+
+/// A food captured from the `GET /api/foods` response
+#[derive(Debug, Clone)]
+pub struct Food {
+    pub id: String,
+    pub price: Option<f64>,
+    pub category: Option<String>,
+}
+
+/// Read the foods in a catalog response: an array of foods, or an object holding
+/// one under `foods`, `items` or `data`. Foods need an `id` (or `food_id`); price
+/// and category (`food_type` or `category`) are optional.
+pub fn parse_catalog(body: &str) -> Vec<Food> {
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(body) else {
+        return Vec::new();
+    };
+    let items = match &value {
+        serde_json::Value::Array(items) => items,
+        serde_json::Value::Object(fields) => match ["foods", "items", "data"]
+            .iter()
+            .find_map(|key| fields.get(*key).and_then(|items| items.as_array()))
+        {
+            Some(items) => items,
+            None => return Vec::new(),
+        },
+        _ => return Vec::new(),
+    };
+
+    let text = |value: &serde_json::Value| match value {
+        serde_json::Value::String(text) => Some(text.clone()),
+        serde_json::Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    };
+    items
+        .iter()
+        .filter_map(|item| {
+            let id = item
+                .get("id")
+                .or_else(|| item.get("food_id"))
+                .and_then(text)?;
+            Some(Food {
+                id,
+                price: item
+                    .get("price")
+                    .and_then(text)
+                    .and_then(|price| price.parse().ok()),
+                category: item
+                    .get("food_type")
+                    .or_else(|| item.get("category"))
+                    .and_then(text),
+            })
+        })
+        .filter(|food| !food.id.is_empty())
+        .collect()
+}
+
+/// Where the food ids used by the built-in scenario came from
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FoodCatalogUsage {
+    /// Picks from the catalog the user just listed
+    pub catalog_picks: usize,
+    /// Picks from the `food_ids` feeder, by why the catalog could not be used
+    pub fallback_picks: BTreeMap<String, usize>,
+}
+
+impl FoodCatalogUsage {
+    pub fn fallbacks(&self) -> usize {
+        self.fallback_picks.values().sum()
+    }
+}
+
+#[derive(Default)]
+pub struct FoodCatalogTracker {
+    usage: Mutex<FoodCatalogUsage>,
+}
+
+impl FoodCatalogTracker {
+    pub fn catalog_pick(&self) {
+        self.usage.lock().unwrap().catalog_picks += 1;
+    }
+
+    pub fn fallback_pick(&self, reason: &str) {
+        *self
+            .usage
+            .lock()
+            .unwrap()
+            .fallback_picks
+            .entry(reason.to_string())
+            .or_default() += 1;
+    }
+
+    pub fn usage(&self) -> FoodCatalogUsage {
+        self.usage.lock().unwrap().clone()
+    }
+}
+
+pub fn display_food_catalog(usage: &FoodCatalogUsage) {
+    println!("{}", "\n🥫 Food Catalog".blue().bold());
+    let fallbacks = usage.fallbacks();
+    println!(
+        "   {} food ids from the live catalog, {} from the fallback list",
+        usage.catalog_picks, fallbacks
+    );
+    if fallbacks == 0 {
+        return;
+    }
+    for (reason, count) in &usage.fallback_picks {
+        println!(
+            "{}",
+            format!("   ⚠ {} x fallback: {}", count, reason).yellow()
+        );
+    }
+}
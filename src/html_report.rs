@@ -0,0 +1,563 @@
+use std::fmt::Write;
+
+use crate::errors::group_errors;
+use crate::report::RunReport;
+use crate::timeseries::{self, TimeSeries, TimeWindow};
+
+const CHART_WIDTH: f64 = 860.0;
+const CHART_HEIGHT: f64 = 240.0;
+const MARGIN_LEFT: f64 = 60.0;
+const MARGIN_RIGHT: f64 = 16.0;
+const MARGIN_TOP: f64 = 28.0;
+const MARGIN_BOTTOM: f64 = 30.0;
+
+const STYLE: &str = "
+body { font-family: -apple-system, 'Segoe UI', Helvetica, Arial, sans-serif; margin: 2rem auto; max-width: 960px; color: #222; }
+h1 { margin-bottom: 0.2rem; }
+h2 { border-bottom: 1px solid #ddd; padding-bottom: 0.3rem; margin-top: 2rem; }
+.meta { color: #666; }
+.cards { display: flex; flex-wrap: wrap; gap: 0.8rem; }
+.card { border: 1px solid #ddd; border-radius: 6px; padding: 0.6rem 1rem; min-width: 120px; }
+.card .value { font-size: 1.4rem; font-weight: 600; }
+.card .label { color: #666; font-size: 0.85rem; }
+table { border-collapse: collapse; width: 100%; font-size: 0.9rem; }
+th, td { border-bottom: 1px solid #eee; padding: 0.3rem 0.5rem; text-align: right; }
+th:first-child, td:first-child { text-align: left; }
+.pass { color: #1a7f37; }
+.fail { color: #cf222e; }
+.aborted { color: #cf222e; border: 1px solid #cf222e; border-radius: 6px; padding: 0.6rem 1rem; font-weight: 600; }
+pre { background: #f6f8fa; padding: 1rem; overflow-x: auto; }
+svg text { font-size: 11px; fill: #555; }
+";
+
+/// One line on a chart
+struct Series<'a> {
+    label: &'a str,
+    color: &'a str,
+    values: Vec<f64>,
+}
+
+/// Escape text for use in HTML element content and attribute values
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn millis(duration: std::time::Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// A line chart over the run timeline with dashed markers where stages start
+fn line_chart(
+    title: &str,
+    unit: &str,
+    windows: &[TimeWindow],
+    width_secs: f64,
+    series: &[Series],
+    markers: &[(String, f64)],
+) -> String {
+    let plot_width = CHART_WIDTH - MARGIN_LEFT - MARGIN_RIGHT;
+    let plot_height = CHART_HEIGHT - MARGIN_TOP - MARGIN_BOTTOM;
+    let max_x = (windows.len() as f64 * width_secs).max(1.0);
+    let max_y = series
+        .iter()
+        .flat_map(|series| series.values.iter().copied())
+        .fold(0.0, f64::max)
+        .max(1.0)
+        * 1.1;
+    let x = |secs: f64| MARGIN_LEFT + secs / max_x * plot_width;
+    let y = |value: f64| MARGIN_TOP + plot_height - value / max_y * plot_height;
+
+    let mut svg = String::new();
+    let _ = write!(
+        svg,
+        r#"<svg viewBox="0 0 {w} {h}" width="100%" role="img" aria-label="{t}">"#,
+        w = CHART_WIDTH,
+        h = CHART_HEIGHT,
+        t = escape(title)
+    );
+    let _ = write!(
+        svg,
+        r#"<text x="{}" y="16" style="font-weight:600;fill:#222">{}</text>"#,
+        MARGIN_LEFT,
+        escape(title)
+    );
+
+    // Horizontal grid with value labels
+    for step in 0..=4 {
+        let value = max_y * step as f64 / 4.0;
+        let _ = write!(
+            svg,
+            "<line x1=\"{x1}\" x2=\"{x2}\" y1=\"{y:.1}\" y2=\"{y:.1}\" stroke=\"#eee\"/>\
+             <text x=\"{lx}\" y=\"{ty:.1}\" text-anchor=\"end\">{value:.0}{unit}</text>",
+            x1 = MARGIN_LEFT,
+            x2 = CHART_WIDTH - MARGIN_RIGHT,
+            y = y(value),
+            lx = MARGIN_LEFT - 6.0,
+            ty = y(value) + 4.0,
+            value = value,
+            unit = unit
+        );
+    }
+    let _ = write!(
+        svg,
+        r#"<text x="{}" y="{}" text-anchor="end">{:.0}s</text>"#,
+        CHART_WIDTH - MARGIN_RIGHT,
+        CHART_HEIGHT - 8.0,
+        max_x
+    );
+
+    for (stage, secs) in markers {
+        let _ = write!(
+            svg,
+            "<line x1=\"{x:.1}\" x2=\"{x:.1}\" y1=\"{top}\" y2=\"{bottom}\" stroke=\"#999\" stroke-dasharray=\"4 3\"/>\
+             <text x=\"{tx:.1}\" y=\"{ty}\">{label}</text>",
+            x = x(*secs),
+            top = MARGIN_TOP,
+            bottom = MARGIN_TOP + plot_height,
+            tx = x(*secs) + 3.0,
+            ty = MARGIN_TOP + 10.0,
+            label = escape(stage)
+        );
+    }
+
+    for (index, series) in series.iter().enumerate() {
+        // Plot each window at its midpoint
+        let points: Vec<String> = series
+            .values
+            .iter()
+            .enumerate()
+            .map(|(window, value)| {
+                format!(
+                    "{:.1},{:.1}",
+                    x((window as f64 + 0.5) * width_secs),
+                    y(*value)
+                )
+            })
+            .collect();
+        let _ = write!(
+            svg,
+            r#"<polyline fill="none" stroke="{}" stroke-width="1.8" points="{}"/>"#,
+            series.color,
+            points.join(" ")
+        );
+        let legend_x = CHART_WIDTH - MARGIN_RIGHT - 70.0 * (index + 1) as f64;
+        let _ = write!(
+            svg,
+            "<rect x=\"{lx:.1}\" y=\"8\" width=\"10\" height=\"10\" fill=\"{color}\"/>\
+             <text x=\"{tx:.1}\" y=\"17\">{label}</text>",
+            lx = legend_x,
+            tx = legend_x + 14.0,
+            color = series.color,
+            label = escape(series.label)
+        );
+    }
+
+    svg.push_str("</svg>");
+    svg
+}
+
+fn card(label: &str, value: &str) -> String {
+    format!(
+        r#"<div class="card"><div class="value">{}</div><div class="label">{}</div></div>"#,
+        escape(value),
+        escape(label)
+    )
+}
+
+/// Render the report as a single self-contained HTML page
+pub fn render(report: &RunReport) -> String {
+    let summary = &report.summary;
+    // Reports saved before time series were recorded still carry every request
+    let recomputed;
+    let series = if report.timeseries.run.is_empty() {
+        recomputed = TimeSeries::from_scenarios(&report.scenarios, summary.total_test_time, None);
+        &recomputed
+    } else {
+        &report.timeseries
+    };
+    let windows = &series.run;
+    let width_secs = series.window.as_secs_f64();
+    let mut markers: Vec<(String, f64)> = timeseries::stage_starts(&report.scenarios)
+        .into_iter()
+        .map(|(stage, start)| (stage, start.as_secs_f64()))
+        .collect();
+    markers.extend(
+        series
+            .knees
+            .iter()
+            .map(|knee| (format!("knee: {}", knee.metric), knee.at.as_secs_f64())),
+    );
+
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html><html lang=\"en\"><head><meta charset=\"utf-8\">\
+         <title>Load test: {scenario}</title><style>{style}</style></head><body>\
+         <h1>Load test: {scenario}</h1>\
+         <p class=\"meta\">Started <time data-unix=\"{started}\">{started}</time> (unix) · \
+         {duration:.1}s · {stages} stage(s){run_id}{seed}</p>",
+        scenario = escape(&report.scenario),
+        style = STYLE,
+        started = report.started_at,
+        duration = summary.total_test_time.as_secs_f64(),
+        stages = report.config.effective_stages().len(),
+        run_id = report
+            .config
+            .run_id
+            .as_ref()
+            .map(|run_id| format!(" · run {}", escape(run_id)))
+            .unwrap_or_default(),
+        seed = report
+            .config
+            .seed
+            .map(|seed| format!(" · seed {}", seed))
+            .unwrap_or_default()
+    );
+    if let Some(abort) = &report.aborted {
+        let _ = write!(
+            html,
+            "<p class=\"aborted\">Run {} at {:.0}s: {} · {} scheduled scenario(s) never started</p>",
+            abort.outcome(),
+            abort.at.as_secs_f64(),
+            escape(&abort.reason),
+            abort.skipped_scenarios
+        );
+    }
+
+    // Summary
+    html.push_str("<h2>Summary</h2><div class=\"cards\">");
+    for (label, value) in [
+        ("Requests", summary.total_requests.to_string()),
+        ("Success rate", format!("{:.1}%", summary.success_rate)),
+        ("Requests/s", format!("{:.1}", summary.requests_per_second)),
+        (
+            "Average",
+            format!("{}ms", summary.average_response_time.as_millis()),
+        ),
+        (
+            "p50",
+            format!("{}ms", summary.p50_response_time.as_millis()),
+        ),
+        (
+            "p95",
+            format!("{}ms", summary.p95_response_time.as_millis()),
+        ),
+        (
+            "p99",
+            format!("{}ms", summary.p99_response_time.as_millis()),
+        ),
+    ] {
+        html.push_str(&card(label, &value));
+    }
+    if !summary.total_limiter_wait.is_zero() {
+        html.push_str(&card(
+            "Avg rate limiter wait",
+            &format!("{}ms", summary.average_limiter_wait.as_millis()),
+        ));
+    }
+    html.push_str("</div>");
+
+    // Charts
+    if !windows.is_empty() {
+        let _ = write!(
+            html,
+            "<h2>Over Time</h2><p class=\"meta\">{}s windows</p>",
+            width_secs
+        );
+        for knee in &series.knees {
+            let _ = write!(
+                html,
+                "<p class=\"fail\">Knee in {} at {:.0}s with {} active users: {:.1} → {:.1}</p>",
+                escape(&knee.metric),
+                knee.at.as_secs_f64(),
+                knee.active_users,
+                knee.baseline,
+                knee.value
+            );
+        }
+        html.push_str(&line_chart(
+            "Throughput",
+            "/s",
+            windows,
+            width_secs,
+            &[
+                Series {
+                    label: "requests/s",
+                    color: "#0969da",
+                    values: windows.iter().map(|w| w.requests_per_second).collect(),
+                },
+                Series {
+                    label: "users",
+                    color: "#8250df",
+                    values: windows.iter().map(|w| w.active_users as f64).collect(),
+                },
+            ],
+            &markers,
+        ));
+        html.push_str(&line_chart(
+            "Latency",
+            "ms",
+            windows,
+            width_secs,
+            &[
+                Series {
+                    label: "p50",
+                    color: "#1a7f37",
+                    values: windows
+                        .iter()
+                        .map(|w| millis(w.p50_response_time))
+                        .collect(),
+                },
+                Series {
+                    label: "p95",
+                    color: "#bf8700",
+                    values: windows
+                        .iter()
+                        .map(|w| millis(w.p95_response_time))
+                        .collect(),
+                },
+                Series {
+                    label: "p99",
+                    color: "#cf222e",
+                    values: windows
+                        .iter()
+                        .map(|w| millis(w.p99_response_time))
+                        .collect(),
+                },
+            ],
+            &markers,
+        ));
+        html.push_str(&line_chart(
+            "Error rate",
+            "%",
+            windows,
+            width_secs,
+            &[Series {
+                label: "errors",
+                color: "#cf222e",
+                values: windows.iter().map(|w| w.error_rate).collect(),
+            }],
+            &markers,
+        ));
+    }
+
+    // Steps
+    html.push_str(
+        "<h2>Steps</h2><table><tr><th>Step</th><th>Requests</th><th>Failed</th>\
+         <th>Error %</th><th>Retries</th><th>Req/s</th><th>Avg</th><th>p50</th><th>p95</th><th>p99</th></tr>",
+    );
+    for step in &summary.steps {
+        let _ = write!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{:.1}</td><td>{}</td><td>{:.1}</td>\
+             <td>{}ms</td><td>{}ms</td><td>{}ms</td><td>{}ms</td></tr>",
+            escape(&step.step),
+            step.total_requests,
+            step.failed_requests,
+            step.error_rate,
+            step.retries,
+            step.requests_per_second,
+            step.average_response_time.as_millis(),
+            step.p50_response_time.as_millis(),
+            step.p95_response_time.as_millis(),
+            step.p99_response_time.as_millis()
+        );
+    }
+    html.push_str("</table>");
+
+    let errors = group_errors(
+        report
+            .scenarios
+            .iter()
+            .flat_map(|scenario| &scenario.requests),
+    );
+    if !errors.is_empty() {
+        html.push_str(
+            "<h2>Errors</h2><table><tr><th>Kind</th><th>Count</th><th>Steps</th>\
+             <th>Example</th></tr>",
+        );
+        for group in errors {
+            let example = match &group.sample_message {
+                Some(message) => format!("{} ({})", group.sample_url, message),
+                None => group.sample_url.clone(),
+            };
+            let _ = write!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td style=\"text-align:left\">{}</td>\
+                 <td style=\"text-align:left\">{}</td></tr>",
+                escape(&group.kind.to_string()),
+                group.count,
+                escape(&group.steps.join(", ")),
+                escape(&example)
+            );
+        }
+        html.push_str("</table>");
+    }
+
+    if !report.thresholds.is_empty() {
+        html.push_str(
+            "<h2>Thresholds</h2><table><tr><th>Scope</th><th>Metric</th>\
+             <th>Observed</th><th>Limit</th><th>Result</th></tr>",
+        );
+        for outcome in &report.thresholds {
+            let observed = outcome
+                .observed
+                .map(|value| format!("{:.1}", value))
+                .unwrap_or_else(|| "no data".to_string());
+            let (class, result) = if outcome.passed {
+                ("pass", "✓ pass")
+            } else {
+                ("fail", "✗ fail")
+            };
+            let _ = write!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{} {}</td><td class=\"{}\">{}</td></tr>",
+                escape(&outcome.scope),
+                escape(&outcome.metric),
+                observed,
+                escape(outcome.operator()),
+                outcome.limit,
+                class,
+                result
+            );
+        }
+        html.push_str("</table>");
+    }
+
+    if !report.regressions.is_empty() {
+        html.push_str(
+            "<h2>Regressions</h2><table><tr><th>Scope</th><th>Metric</th>\
+             <th>Baseline</th><th>Candidate</th><th>p-value</th></tr>",
+        );
+        for regression in &report.regressions {
+            let _ = write!(
+                html,
+                "<tr class=\"fail\"><td>{}</td><td>{}</td><td>{:.1}</td><td>{:.1}</td><td>{}</td></tr>",
+                escape(&regression.scope),
+                escape(&regression.metric),
+                regression.baseline,
+                regression.candidate,
+                regression
+                    .p_value
+                    .map(|p_value| format!("{:.4}", p_value))
+                    .unwrap_or_else(|| "-".to_string())
+            );
+        }
+        html.push_str("</table>");
+    }
+
+    if let Some(cleanup) = &report.cleanup {
+        html.push_str("<h2>Cleanup</h2><div class=\"cards\">");
+        let failed_users = cleanup.failed_users();
+        for (label, value) in [
+            ("Users", cleanup.users.len().to_string()),
+            ("Requests", cleanup.requests.len().to_string()),
+            (
+                "Failed",
+                cleanup
+                    .requests
+                    .iter()
+                    .filter(|r| !r.success)
+                    .count()
+                    .to_string(),
+            ),
+            (
+                "Duration",
+                format!("{:.1}s", cleanup.duration.as_secs_f64()),
+            ),
+        ] {
+            html.push_str(&card(label, &value));
+        }
+        html.push_str("</div>");
+        if !failed_users.is_empty() {
+            let _ = write!(
+                html,
+                "<p class=\"fail\">Users that may still have data: {}</p>",
+                escape(&failed_users.join(", "))
+            );
+        }
+        let uncleanable_users = cleanup.uncleanable_users();
+        if !uncleanable_users.is_empty() {
+            let _ = write!(
+                html,
+                "<p class=\"fail\">Users that can't be cleaned up (a value their cleanup needs was never captured): {}</p>",
+                escape(&uncleanable_users.join(", "))
+            );
+        }
+    }
+
+    if let Some(usage) = &report.food_catalog {
+        html.push_str("<h2>Food Catalog</h2><div class=\"cards\">");
+        html.push_str(&card("From catalog", &usage.catalog_picks.to_string()));
+        html.push_str(&card("From fallback list", &usage.fallbacks().to_string()));
+        html.push_str("</div>");
+        for (reason, count) in &usage.fallback_picks {
+            let _ = write!(
+                html,
+                "<p class=\"fail\">{} x fallback: {}</p>",
+                count,
+                escape(reason)
+            );
+        }
+    }
+
+    if let Some(usage) = &report.connections {
+        let _ = write!(
+            html,
+            "<h2>Connections ({})</h2><div class=\"cards\">",
+            usage.mode.name()
+        );
+        for (label, value) in [
+            ("Opened", usage.opened.to_string()),
+            ("HTTP requests", usage.exchanges.to_string()),
+            ("Requests per connection", format!("{:.1}", usage.reuse())),
+            (
+                "Avg connect",
+                format!("{}ms", usage.average_connect_time.as_millis()),
+            ),
+            (
+                "p95 connect",
+                format!("{}ms", usage.p95_connect_time.as_millis()),
+            ),
+            (
+                "Max connect",
+                format!("{}ms", usage.max_connect_time.as_millis()),
+            ),
+        ] {
+            html.push_str(&card(label, &value));
+        }
+        html.push_str(
+            "</div><p>Connect time covers DNS lookup, TCP connect and TLS handshake.</p>",
+        );
+    }
+
+    html.push_str("<h2>Endpoints</h2><table>");
+    for (service, url) in &report.endpoints {
+        let _ = write!(
+            html,
+            "<tr><td>{}</td><td style=\"text-align:left\">{}</td></tr>",
+            escape(service),
+            escape(url)
+        );
+    }
+    html.push_str("</table>");
+
+    let config = report
+        .config
+        .to_toml()
+        .unwrap_or_else(|err| format!("# unable to render config: {}", err));
+    let _ = write!(
+        html,
+        "<h2>Configuration</h2><pre>{}</pre>\
+         <script>document.querySelectorAll('time[data-unix]').forEach(function (t) {{\
+         t.textContent = new Date(t.dataset.unix * 1000).toLocaleString(); }});</script>\
+         </body></html>",
+        escape(&config)
+    );
+    html.push('\n');
+
+    html
+}
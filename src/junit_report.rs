@@ -0,0 +1,305 @@
+use std::fmt::Write;
+
+use crate::config::RunConfig;
+use crate::errors::group_errors;
+use crate::report::RunReport;
+use crate::types::RequestResult;
+
+/// One `<testcase>`; `failure` holds the message and details when it failed
+struct TestCase {
+    name: String,
+    classname: String,
+    time_secs: f64,
+    failure: Option<(String, String)>,
+}
+
+/// Escape text for XML element content and attribute values
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Threshold outcomes, one test case each
+fn threshold_cases(report: &RunReport) -> Vec<TestCase> {
+    report
+        .thresholds
+        .iter()
+        .map(|outcome| {
+            let observed = outcome
+                .observed
+                .map(|value| format!("{:.1}", value))
+                .unwrap_or_else(|| "no data".to_string());
+            TestCase {
+                name: format!("{} {}", outcome.scope, outcome.metric),
+                classname: format!("{}.thresholds", report.scenario),
+                time_secs: 0.0,
+                failure: (!outcome.passed).then(|| {
+                    (
+                        format!(
+                            "{} {}: observed {}, limit {} {}",
+                            outcome.scope,
+                            outcome.metric,
+                            observed,
+                            outcome.operator(),
+                            outcome.limit
+                        ),
+                        format!(
+                            "scope={}\nmetric={}\nobserved={}\nlimit={} {}",
+                            outcome.scope,
+                            outcome.metric,
+                            observed,
+                            outcome.operator(),
+                            outcome.limit
+                        ),
+                    )
+                }),
+            }
+        })
+        .collect()
+}
+
+/// Every scenario step asserts that all of its requests succeeded
+fn step_cases(report: &RunReport) -> Vec<TestCase> {
+    report
+        .summary
+        .steps
+        .iter()
+        .map(|step| {
+            let requests: Vec<&RequestResult> = report
+                .scenarios
+                .iter()
+                .flat_map(|scenario| &scenario.requests)
+                .filter(|request| request.step == step.step)
+                .collect();
+
+            let reasons = group_errors(requests.iter().copied());
+
+            let details = format!(
+                "requests={}\nfailed={}\nerror_rate={:.1}%\nretries={}\np50={}ms\np95={}ms\np99={}ms\n{}",
+                step.total_requests,
+                step.failed_requests,
+                step.error_rate,
+                step.retries,
+                step.p50_response_time.as_millis(),
+                step.p95_response_time.as_millis(),
+                step.p99_response_time.as_millis(),
+                reasons
+                    .iter()
+                    .map(|group| format!(
+                        "{} x {} (e.g. {})",
+                        group.count, group.kind, group.sample_url
+                    ))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            );
+
+            TestCase {
+                name: step.step.clone(),
+                classname: format!("{}.steps", report.scenario),
+                time_secs: requests
+                    .iter()
+                    .map(|request| request.response_time.as_secs_f64())
+                    .sum(),
+                failure: (step.failed_requests > 0).then(|| {
+                    (
+                        format!(
+                            "{}/{} requests failed ({:.1}%)",
+                            step.failed_requests, step.total_requests, step.error_rate
+                        ),
+                        details,
+                    )
+                }),
+            }
+        })
+        .collect()
+}
+
+/// Regressions against the baseline run; a passing case when there were none
+fn regression_cases(report: &RunReport) -> Vec<TestCase> {
+    if report.config.baseline.is_none() {
+        return Vec::new();
+    }
+    if report.regressions.is_empty() {
+        return vec![TestCase {
+            name: "no regressions".to_string(),
+            classname: format!("{}.baseline", report.scenario),
+            time_secs: 0.0,
+            failure: None,
+        }];
+    }
+
+    report
+        .regressions
+        .iter()
+        .map(|regression| TestCase {
+            name: format!("{} {}", regression.scope, regression.metric),
+            classname: format!("{}.baseline", report.scenario),
+            time_secs: 0.0,
+            failure: Some((
+                format!(
+                    "{} {} regressed: {:.1} → {:.1}",
+                    regression.scope, regression.metric, regression.baseline, regression.candidate
+                ),
+                match regression.p_value {
+                    Some(p_value) => format!("p-value={:.4}", p_value),
+                    None => String::new(),
+                },
+            )),
+        })
+        .collect()
+}
+
+/// Whether the run finished its load profile; only reported when it could have aborted
+fn abort_cases(report: &RunReport) -> Vec<TestCase> {
+    if report.config.abort.conditions.is_empty() && report.aborted.is_none() {
+        return Vec::new();
+    }
+    vec![TestCase {
+        name: "not aborted".to_string(),
+        classname: format!("{}.run", report.scenario),
+        time_secs: 0.0,
+        failure: report.aborted.as_ref().map(|abort| {
+            (
+                format!(
+                    "run {} at {:.0}s: {}",
+                    abort.outcome(),
+                    abort.at.as_secs_f64(),
+                    abort.reason
+                ),
+                format!(
+                    "reason={}\nat={:.0}s\nskipped_scenarios={}\ninterrupted={}\nexhausted={}",
+                    abort.reason,
+                    abort.at.as_secs_f64(),
+                    abort.skipped_scenarios,
+                    abort.interrupted,
+                    abort.exhausted
+                ),
+            )
+        }),
+    }]
+}
+
+/// The cleanup phase passes when every user's cleanup requests succeeded
+fn cleanup_cases(report: &RunReport) -> Vec<TestCase> {
+    let Some(cleanup) = &report.cleanup else {
+        return Vec::new();
+    };
+    let failed_users = cleanup.failed_users();
+    let uncleanable_users = cleanup.uncleanable_users();
+    vec![TestCase {
+        name: "all users cleaned up".to_string(),
+        classname: format!("{}.cleanup", report.scenario),
+        time_secs: cleanup.duration.as_secs_f64(),
+        failure: (!failed_users.is_empty() || !uncleanable_users.is_empty()).then(|| {
+            (
+                format!(
+                    "{} users may still have data",
+                    failed_users.len() + uncleanable_users.len()
+                ),
+                format!(
+                    "users={}\nrequests={}\nfailed_users={}\nuncleanable_users={}",
+                    cleanup.users.len(),
+                    cleanup.requests.len(),
+                    failed_users.join(","),
+                    uncleanable_users.join(",")
+                ),
+            )
+        }),
+    }]
+}
+
+fn write_suite(xml: &mut String, name: &str, config: &RunConfig, cases: &[TestCase]) {
+    let failures = cases.iter().filter(|case| case.failure.is_some()).count();
+    let time: f64 = cases.iter().map(|case| case.time_secs).sum();
+    let _ = writeln!(
+        xml,
+        r#"  <testsuite name="{}" tests="{}" failures="{}" errors="0" time="{:.3}">"#,
+        escape(name),
+        cases.len(),
+        failures,
+        time
+    );
+    let properties: Vec<(&str, String)> = [
+        ("run_id", config.run_id.clone()),
+        ("seed", config.seed.map(|seed| seed.to_string())),
+    ]
+    .into_iter()
+    .filter_map(|(name, value)| Some((name, value?)))
+    .collect();
+    if !properties.is_empty() {
+        xml.push_str("    <properties>");
+        for (name, value) in properties {
+            let _ = write!(
+                xml,
+                r#"<property name="{}" value="{}"/>"#,
+                name,
+                escape(&value)
+            );
+        }
+        xml.push_str("</properties>\n");
+    }
+    for case in cases {
+        let _ = write!(
+            xml,
+            r#"    <testcase name="{}" classname="{}" time="{:.3}""#,
+            escape(&case.name),
+            escape(&case.classname),
+            case.time_secs
+        );
+        match &case.failure {
+            Some((message, details)) => {
+                let _ = writeln!(
+                    xml,
+                    ">\n      <failure message=\"{}\">{}</failure>\n    </testcase>",
+                    escape(message),
+                    escape(details)
+                );
+            }
+            None => xml.push_str("/>\n"),
+        }
+    }
+    xml.push_str("  </testsuite>\n");
+}
+
+/// Render the abort check, thresholds, step assertions, baseline regressions and cleanup as JUnit XML
+pub fn render(report: &RunReport) -> String {
+    let suites = [
+        ("run", abort_cases(report)),
+        ("thresholds", threshold_cases(report)),
+        ("steps", step_cases(report)),
+        ("baseline", regression_cases(report)),
+        ("cleanup", cleanup_cases(report)),
+    ];
+
+    let tests: usize = suites.iter().map(|(_, cases)| cases.len()).sum();
+    let failures: usize = suites
+        .iter()
+        .flat_map(|(_, cases)| cases)
+        .filter(|case| case.failure.is_some())
+        .count();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        r#"<testsuites name="load test: {}" tests="{}" failures="{}" time="{:.3}">"#,
+        escape(&report.scenario),
+        tests,
+        failures,
+        report.summary.total_test_time.as_secs_f64()
+    );
+    for (name, cases) in &suites {
+        if !cases.is_empty() {
+            write_suite(
+                &mut xml,
+                &format!("{}.{}", report.scenario, name),
+                &report.config,
+                cases,
+            );
+        }
+    }
+    xml.push_str("</testsuites>\n");
+    xml
+}
@@ -1,192 +1,444 @@
+use aws_config::SdkConfig;
 use colored::*;
 use futures::future::join_all;
+use futures::stream::{self, StreamExt};
+use rand::rngs::StdRng;
 use rand::Rng;
-use reqwest::Client;
-use serde_json;
+use reqwest::cookie::CookieStore;
+use reqwest::header::{CONTENT_TYPE, COOKIE, LOCATION, SET_COOKIE};
+use reqwest::{Method, StatusCode, Url};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::time::timeout;
 
+use crate::abort::{Abort, CircuitBreaker, StopSignal};
+use crate::auth::Auth;
+use crate::cleanup::{CleanupResult, CleanupTracker};
+use crate::config::{RunConfig, Stage};
+use crate::errors::ErrorKind;
+use crate::feeders::{Feeders, Record};
+use crate::food_catalog::{self, FoodCatalogConfig, FoodCatalogTracker, FoodCatalogUsage};
+use crate::payment::PaymentConfig;
+use crate::rate_limit::RateLimiter;
+use crate::retry::RetryConfig;
+use crate::scenario::{Scenario, ScenarioDefinition, StepDefinition};
+use crate::seed;
+use crate::session::{self, ConnectionUsage, Sessions};
+use crate::template::{self, Expr, Vars};
 use crate::types::*;
+use crate::users::{self, UserIdConfig};
+
+/// Redirects followed before a request fails, as reqwest's default policy allows
+const MAX_REDIRECTS: usize = 10;
+
+/// The response a request needs to count as a success, instead of any 2xx or 3xx
+#[derive(Clone, Copy)]
+enum Expect {
+    Status(u16),
+    /// Any 4xx, for requests that are invalid on purpose
+    ClientError,
+}
+
+/// One request, its templates already rendered
+struct Request<'a> {
+    step: &'a str,
+    /// Where the request goes, which picks its credentials and rate limit
+    service: &'a str,
+    method: &'a str,
+    url: String,
+    user_id: &'a str,
+    headers: Vec<(String, String)>,
+    body: Option<serde_json::Value>,
+    /// Checked instead of any 2xx or 3xx passing
+    expect: Option<Expect>,
+}
+
+impl Request<'_> {
+    /// Set a header, replacing any of the same name
+    fn header(&mut self, name: &str, value: String) {
+        self.headers
+            .retain(|(existing, _)| !existing.eq_ignore_ascii_case(name));
+        self.headers.push((name.to_string(), value));
+    }
+}
+
+/// State a PetSite user changed, which cleanup has to undo
+#[derive(Default)]
+struct UserChanges {
+    adoptions: bool,
+    cart: bool,
+    /// The scenario's key in the cleanup tracker, once it changed something
+    tracked: Option<usize>,
+}
 
 pub struct LoadTester {
-    user_count: usize,
-    concurrent_requests: usize,
+    stages: Vec<Stage>,
     endpoints: Endpoints,
+    scenario: Scenario,
     dry_run: bool,
     verbose: bool,
-    rampup_seconds: u64,
-    client: Client,
+    request_timeout: Duration,
+    retry: RetryConfig,
+    limiter: RateLimiter,
+    /// Each virtual user's client and cookies
+    sessions: Sessions,
+    /// Reference point for each request's `started_at`
+    started: Instant,
+    breaker: Arc<CircuitBreaker>,
+    /// Set when the run should stop starting scenarios
+    stop: Arc<StopSignal>,
+    /// Scenarios that were scheduled but never started because of a stop
+    skipped: AtomicUsize,
+    /// Users whose changes still need removing
+    cleanup: CleanupTracker,
+    cleanup_concurrency: usize,
+    /// Set once the cleanup phase starts; nothing it does may stop the run
+    cleaning: AtomicBool,
+    feeders: Feeders,
+    food_catalog: FoodCatalogConfig,
+    /// Where the built-in scenario's food ids came from
+    catalog_usage: FoodCatalogTracker,
+    /// Every user id and random choice derives from this
+    seed: u64,
+    run_id: String,
+    user_ids: UserIdConfig,
+    payment: PaymentConfig,
+    /// Sent with every request, rendered per request
+    headers: BTreeMap<String, String>,
+    auth: Auth,
 }
 
 impl LoadTester {
     pub fn new(
-        user_count: usize,
-        concurrent_requests: usize,
+        config: &RunConfig,
         endpoints: Endpoints,
+        scenario: Scenario,
         dry_run: bool,
         verbose: bool,
-        rampup_seconds: u64,
-    ) -> Self {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(10))
-            .build()
-            .expect("Failed to create HTTP client");
-
-        Self {
-            user_count,
-            concurrent_requests,
+        aws: Option<&SdkConfig>,
+    ) -> anyhow::Result<Self> {
+        let request_timeout = Duration::from_secs(config.http.timeout_secs);
+        let auth = Auth::new(&config.auth, &config.region, request_timeout, aws)?;
+        let sessions = Sessions::new(&config.http)?;
+        let started = Instant::now();
+        let feeders = Feeders::load(config, &scenario)?;
+
+        Ok(Self {
+            stages: config.effective_stages(),
             endpoints,
+            scenario,
             dry_run,
             verbose,
-            rampup_seconds,
-            client,
-        }
+            request_timeout,
+            retry: config.retry.clone(),
+            limiter: RateLimiter::new(&config.rate_limit),
+            sessions,
+            started,
+            breaker: Arc::new(CircuitBreaker::new(&config.abort, started)),
+            stop: Arc::new(StopSignal::new(started)),
+            skipped: AtomicUsize::new(0),
+            cleanup: CleanupTracker::new(config.cleanup.users_file.as_deref())?,
+            cleanup_concurrency: config.cleanup.concurrency,
+            cleaning: AtomicBool::new(false),
+            feeders,
+            food_catalog: config.food_catalog.clone(),
+            catalog_usage: FoodCatalogTracker::default(),
+            seed: config.seed.unwrap_or_else(seed::random_seed),
+            run_id: config.run_id.clone().unwrap_or_else(users::new_run_id),
+            user_ids: config.user_ids.clone(),
+            payment: config.payment.clone(),
+            headers: config.http.headers.clone(),
+            auth,
+        })
+    }
+
+    /// The generator for one scenario run: the same for a given seed, user
+    /// position and round, whatever the user's id
+    fn scenario_rng(&self, user: usize, round: usize) -> StdRng {
+        seed::rng_for(self.seed, &["user", &user.to_string(), &round.to_string()])
     }
 
-    fn generate_users(&self) -> Vec<String> {
-        use rand::Rng;
-        use std::collections::HashSet;
+    pub async fn run_load_test(&self) -> anyhow::Result<Vec<UserScenarioResult>> {
+        println!("{}", "\n🎯 Starting load test...".blue());
 
-        let mut rng = rand::thread_rng();
-        let mut user_ids = HashSet::new();
+        let breaker_task = self.breaker.is_enabled().then(|| {
+            let breaker = self.breaker.clone();
+            let stop = self.stop.clone();
+            tokio::spawn(async move {
+                loop {
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    if stop.is_stopped() {
+                        break;
+                    }
+                    if let Some(reason) = breaker.check() {
+                        println!(
+                            "{}",
+                            format!(
+                                "🛑 Abort condition met: {} - no new scenarios will start, waiting for in-flight ones",
+                                reason
+                            )
+                            .red()
+                            .bold()
+                        );
+                        stop.stop(reason);
+                        break;
+                    }
+                }
+            })
+        });
+
+        // Every stage gets its own users
+        let total_users = self.stages.iter().map(|stage| stage.users).sum();
+        let user_ids = self
+            .user_ids
+            .generate(total_users, self.seed, &self.run_id)?;
+        let mut first_user = 0;
+
+        let mut results = Vec::new();
+        for stage in &self.stages {
+            let users = &user_ids[first_user..first_user + stage.users];
+            let stage_first_user = first_user;
+            first_user += stage.users;
+            if self.stop.is_stopped() {
+                self.skipped
+                    .fetch_add(stage.users * stage.concurrent, Ordering::Relaxed);
+                continue;
+            }
+            if self.stages.len() > 1 {
+                println!(
+                    "{}",
+                    format!(
+                        "\n🎬 Stage '{}': {} users × {} concurrent, {}s ramp-up",
+                        stage.name, stage.users, stage.concurrent, stage.rampup
+                    )
+                    .blue()
+                    .bold()
+                );
+            }
+            results.extend(self.run_stage(stage, users, stage_first_user).await?);
+        }
 
-        // Generate unique random user IDs
-        while user_ids.len() < self.user_count {
-            let random_id = rng.gen_range(10000..99999);
-            user_ids.insert(format!("user{:05}", random_id));
+        if let Some(task) = breaker_task {
+            task.abort();
         }
+        Ok(results)
+    }
 
-        user_ids.into_iter().collect()
+    /// Why the run stopped early, once `run_load_test` has returned
+    pub fn abort(&self) -> Option<Abort> {
+        self.stop.reason().map(|(reason, at)| Abort {
+            reason,
+            at,
+            skipped_scenarios: self.skipped.load(Ordering::Relaxed),
+            interrupted: self.stop.is_interrupted(),
+            exhausted: self.stop.is_exhausted(),
+        })
     }
 
-    pub async fn run_load_test(&self) -> anyhow::Result<Vec<UserScenarioResult>> {
-        println!("{}", "\n🎯 Starting load test...".blue());
+    /// Where the built-in scenario's food ids came from; `None` for custom scenarios
+    pub fn food_catalog_usage(&self) -> Option<FoodCatalogUsage> {
+        matches!(self.scenario, Scenario::PetSite).then(|| self.catalog_usage.usage())
+    }
+
+    /// Connections opened and how long they took; `None` for a dry run
+    pub fn connection_usage(&self) -> Option<ConnectionUsage> {
+        (!self.dry_run).then(|| self.sessions.usage())
+    }
+
+    /// Handle for stopping the run from outside, e.g. on a signal
+    pub fn stop_signal(&self) -> Arc<StopSignal> {
+        self.stop.clone()
+    }
+
+    /// Users that changed state during the run and were not cleaned up by their scenario
+    pub fn pending_cleanup(&self) -> Vec<String> {
+        self.cleanup.pending()
+    }
+
+    /// Send every cleanup step for each of `users`, `cleanup.concurrency` users at a time
+    pub async fn run_cleanup(&self, users: Vec<String>) -> CleanupResult {
+        self.cleaning.store(true, Ordering::Relaxed);
+        let start_time = Instant::now();
+        let requests: Vec<Vec<RequestResult>> = stream::iter(&users)
+            .map(|user_id| self.cleanup_user(user_id))
+            .buffer_unordered(self.cleanup_concurrency)
+            .collect()
+            .await;
 
-        let users = self.generate_users();
-        let total_scenarios = self.user_count * self.concurrent_requests;
+        CleanupResult {
+            users,
+            requests: requests.into_iter().flatten().collect(),
+            duration: start_time.elapsed(),
+        }
+    }
+
+    async fn cleanup_user(&self, user_id: &str) -> Vec<RequestResult> {
+        let mut rng = seed::rng_for(self.seed, &[user_id, "cleanup"]);
+        let requests = match &self.scenario {
+            Scenario::PetSite => {
+                self.run_petsite_cleanup(user_id, true, true, &mut rng)
+                    .await
+            }
+            Scenario::File(definition) => {
+                // Each unfinished scenario is undone with what it captured. Users
+                // this run didn't track have nothing, so steps using variables are skipped
+                let mut captures = self.cleanup.captures(user_id);
+                if captures.is_empty() {
+                    captures.push(Vars::new());
+                }
+                let mut requests = Vec::new();
+                for mut vars in captures {
+                    let mut rows = HashMap::new();
+                    for step in definition.steps.iter().filter(|step| step.cleanup) {
+                        requests.push(
+                            self.run_file_step(step, user_id, &mut rows, &mut vars, &mut rng)
+                                .await,
+                        );
+                    }
+                }
+                requests
+            }
+        };
+        // A skipped step can never be sent, so the user is reported rather than kept for a retry
+        if requests
+            .iter()
+            .all(|r| r.success || r.error_kind == Some(ErrorKind::Skipped))
+        {
+            self.cleanup.cleared(user_id);
+        }
+        requests
+    }
+
+    /// Run `stage` with `users`, the run's users from position `first_user` on
+    async fn run_stage(
+        &self,
+        stage: &Stage,
+        users: &[String],
+        first_user: usize,
+    ) -> anyhow::Result<Vec<UserScenarioResult>> {
+        let total_scenarios = stage.users * stage.concurrent;
 
-        if self.rampup_seconds > 0 {
+        let rampup_interval = if stage.rampup > 0 {
+            let interval = Duration::from_millis((stage.rampup * 1000) / total_scenarios as u64);
             println!(
                 "{}",
                 format!(
                     "📈 Ramping up {} scenarios over {} seconds...",
-                    total_scenarios, self.rampup_seconds
+                    total_scenarios, stage.rampup
                 )
                 .cyan()
             );
-            self.run_rampup_test(users).await
+            println!(
+                "{}",
+                format!("⏱️  Starting new scenario every {}ms", interval.as_millis()).purple()
+            );
+            interval
         } else {
             println!(
                 "{}",
                 format!("⚡ Running {} concurrent scenarios...", total_scenarios).yellow()
             );
-            self.run_immediate_test(users).await
-        }
-    }
+            Duration::ZERO
+        };
 
-    async fn run_immediate_test(
-        &self,
-        users: Vec<String>,
-    ) -> anyhow::Result<Vec<UserScenarioResult>> {
+        // Every scenario is polled from the start and waits for its own slot,
+        // so ramped-up scenarios really start spread out and a stop can cancel
+        // the ones still waiting
+        let start_time = Instant::now();
         let mut all_futures = Vec::new();
-
-        // Create concurrent futures for all user scenarios
-        for _ in 0..self.concurrent_requests {
-            for user_id in &users {
-                let future = self.run_scenario_for_user(user_id.clone());
-                all_futures.push(future);
+        for round in 0..stage.concurrent {
+            for (index, user_id) in users.iter().enumerate() {
+                let scenario_number = all_futures.len() + 1;
+                let delay = rampup_interval * (scenario_number - 1) as u32;
+                all_futures.push(self.run_scheduled_scenario(
+                    stage,
+                    user_id.clone(),
+                    self.scenario_rng(first_user + index, round),
+                    scenario_number,
+                    delay,
+                    start_time,
+                ));
             }
         }
 
-        let results = join_all(all_futures).await;
-        Ok(results)
+        self.run_with_progress_monitoring(all_futures, total_scenarios, start_time)
+            .await
     }
 
-    async fn run_rampup_test(&self, users: Vec<String>) -> anyhow::Result<Vec<UserScenarioResult>> {
-        use tokio::time::{sleep, Duration};
-
-        let total_scenarios = self.user_count * self.concurrent_requests;
-        let rampup_interval =
-            Duration::from_millis((self.rampup_seconds * 1000) / total_scenarios as u64);
-
-        let mut all_futures = Vec::new();
-        let mut scenario_count = 0;
-        let start_time = std::time::Instant::now();
-
-        println!(
-            "{}",
-            format!(
-                "⏱️  Starting new scenario every {}ms",
-                rampup_interval.as_millis()
-            )
-            .purple()
-        );
-
-        // Ramp up scenarios gradually
-        for round in 0..self.concurrent_requests {
-            for user_id in &users {
-                scenario_count += 1;
-
-                // Show progress for verbose mode or every 10 scenarios or first few
-                if self.verbose || scenario_count % 10 == 0 || scenario_count <= 5 {
-                    println!(
-                        "{}",
-                        format!(
-                            "[RAMP-UP] Starting scenario {}/{} for {} ({}s elapsed)",
-                            scenario_count,
-                            total_scenarios,
-                            user_id,
-                            start_time.elapsed().as_secs()
-                        )
-                        .purple()
-                    );
-                }
+    /// Wait `delay`, then run the scenario unless the run was stopped meanwhile
+    async fn run_scheduled_scenario(
+        &self,
+        stage: &Stage,
+        user_id: String,
+        rng: StdRng,
+        scenario_number: usize,
+        delay: Duration,
+        start_time: Instant,
+    ) -> Option<UserScenarioResult> {
+        let total_scenarios = stage.users * stage.concurrent;
+        if !delay.is_zero() {
+            // Wakes early when the run is stopped
+            let _ = timeout(delay, self.stop.stopped()).await;
+        }
+        if self.stop.is_stopped() {
+            self.skipped.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
 
-                // Show milestone progress for large tests
-                if scenario_count % 100 == 0 || (scenario_count % 50 == 0 && total_scenarios > 200)
-                {
-                    let progress_pct = (scenario_count as f64 / total_scenarios as f64) * 100.0;
-                    println!(
-                        "{}",
-                        format!(
-                            "📈 Ramp-up Progress: {:.1}% ({}/{}) - {}s elapsed",
-                            progress_pct,
-                            scenario_count,
-                            total_scenarios,
-                            start_time.elapsed().as_secs()
-                        )
-                        .cyan()
-                    );
-                }
+        if stage.rampup > 0 {
+            // Show progress for verbose mode or every 10 scenarios or first few
+            if self.verbose || scenario_number.is_multiple_of(10) || scenario_number <= 5 {
+                println!(
+                    "{}",
+                    format!(
+                        "[RAMP-UP] Starting scenario {}/{} for {} ({}s elapsed)",
+                        scenario_number,
+                        total_scenarios,
+                        user_id,
+                        start_time.elapsed().as_secs()
+                    )
+                    .purple()
+                );
+            }
 
-                // Start the scenario without spawning a task
-                let future = self.run_scenario_for_user(user_id.clone());
-                all_futures.push(future);
+            // Show milestone progress for large tests
+            if scenario_number.is_multiple_of(100)
+                || (scenario_number.is_multiple_of(50) && total_scenarios > 200)
+            {
+                let progress_pct = (scenario_number as f64 / total_scenarios as f64) * 100.0;
+                println!(
+                    "{}",
+                    format!(
+                        "📈 Ramp-up Progress: {:.1}% ({}/{}) - {}s elapsed",
+                        progress_pct,
+                        scenario_number,
+                        total_scenarios,
+                        start_time.elapsed().as_secs()
+                    )
+                    .cyan()
+                );
+            }
 
-                // Sleep between scenario starts (except for the last one)
-                if scenario_count < total_scenarios {
-                    sleep(rampup_interval).await;
-                }
+            if scenario_number == total_scenarios {
+                println!(
+                    "{}",
+                    format!(
+                        "🚀 All {} scenarios started, waiting for completion...",
+                        total_scenarios
+                    )
+                    .green()
+                );
             }
         }
 
-        println!(
-            "{}",
-            format!(
-                "🚀 All {} scenarios started, waiting for completion...",
-                total_scenarios
-            )
-            .green()
-        );
-
-        // Always use progress monitoring for better user experience
-        self.run_with_progress_monitoring(all_futures, total_scenarios, start_time)
-            .await
+        Some(self.run_scenario_for_user(&stage.name, user_id, rng).await)
     }
 
     async fn run_with_progress_monitoring(
         &self,
-        all_futures: Vec<impl std::future::Future<Output = UserScenarioResult>>,
+        all_futures: Vec<impl std::future::Future<Output = Option<UserScenarioResult>>>,
         total_scenarios: usize,
         start_time: Instant,
     ) -> anyhow::Result<Vec<UserScenarioResult>> {
@@ -208,11 +460,12 @@ impl LoadTester {
                 async move {
                     let result = future.await;
 
-                    // Update counters
+                    // Update counters; scenarios skipped by a stop count as done
                     {
                         let mut count = completed_count.lock().unwrap();
                         *count += 1;
                     }
+                    let result = result?;
                     {
                         let mut total = total_requests.lock().unwrap();
                         *total += result.requests.len();
@@ -222,7 +475,7 @@ impl LoadTester {
                         *failed += result.requests.iter().filter(|r| !r.success).count();
                     }
 
-                    result
+                    Some(result)
                 }
             })
             .collect();
@@ -278,7 +531,7 @@ impl LoadTester {
                         "{}",
                         format!(
                             "📊 Execution Progress: {:.1}% ({}/{}) | {} requests ({} failed) | {:.1} req/s | {}s elapsed",
-                            progress_pct, completed, total_scenarios, 
+                            progress_pct, completed, total_scenarios,
                             total_reqs, failed_reqs, rps, elapsed.as_secs()
                         ).cyan()
                     );
@@ -287,7 +540,11 @@ impl LoadTester {
         };
 
         // Wait for all scenarios to complete
-        let results = join_all(monitored_futures).await;
+        let results: Vec<UserScenarioResult> = join_all(monitored_futures)
+            .await
+            .into_iter()
+            .flatten()
+            .collect();
 
         // Stop progress monitoring
         progress_task.abort();
@@ -306,6 +563,22 @@ impl LoadTester {
             *failed
         };
 
+        if results.len() < final_completed {
+            println!(
+                "{}",
+                format!(
+                    "🛑 Stopped early: {}/{} scenarios ran | {} total requests ({} successful, {} failed)",
+                    results.len(),
+                    total_scenarios,
+                    final_total_reqs,
+                    final_total_reqs - final_failed_reqs,
+                    final_failed_reqs
+                )
+                .yellow()
+            );
+            return Ok(results);
+        }
+
         println!(
             "{}",
             format!(
@@ -322,42 +595,402 @@ impl LoadTester {
         Ok(results)
     }
 
-    async fn run_scenario_for_user(&self, user_id: String) -> UserScenarioResult {
+    async fn run_scenario_for_user(
+        &self,
+        stage: &str,
+        user_id: String,
+        mut rng: StdRng,
+    ) -> UserScenarioResult {
         let start_time = Instant::now();
+
+        let requests = match &self.scenario {
+            Scenario::PetSite => self.run_petsite_steps(&user_id, &mut rng).await,
+            Scenario::File(definition) => self.run_file_steps(definition, &user_id, &mut rng).await,
+        };
+
+        // Check if any request failed
+        let success = requests.iter().all(|r| r.success);
+        let error = if success {
+            None
+        } else {
+            Some("One or more requests failed".to_string())
+        };
+
+        UserScenarioResult {
+            stage: stage.to_string(),
+            user_id,
+            requests,
+            total_time: start_time.elapsed(),
+            success,
+            error,
+        }
+    }
+
+    async fn run_file_steps(
+        &self,
+        definition: &ScenarioDefinition,
+        user_id: &str,
+        rng: &mut StdRng,
+    ) -> Vec<RequestResult> {
         let mut requests = Vec::new();
-        let mut success = true;
-        let mut error = None;
+        let has_cleanup = definition.steps.iter().any(|step| step.cleanup);
+        let mut changed_state = false;
+        // The scenario's key in the cleanup tracker, once it changed something
+        let mut tracked = None;
+        let mut cleaned = true;
+        let mut rows = HashMap::new();
+        let mut vars = Vars::new();
+
+        for step in &definition.steps {
+            // After an interrupt only cleanup steps run, and only for users that changed something
+            if self.stop.is_interrupted() && !(step.cleanup && changed_state) {
+                continue;
+            }
+            if !step.cleanup
+                && !changed_state
+                && !matches!(step.method.as_str(), "GET" | "HEAD" | "OPTIONS")
+            {
+                changed_state = true;
+                if has_cleanup {
+                    let scenario = self.cleanup.touched(user_id);
+                    self.cleanup.captured(user_id, scenario, &vars);
+                    tracked = Some(scenario);
+                }
+            }
+            let result = self
+                .run_file_step(step, user_id, &mut rows, &mut vars, rng)
+                .await;
+            if step.cleanup {
+                cleaned &= result.success;
+            }
+            if let Some(scenario) = tracked.filter(|_| !step.capture.is_empty()) {
+                self.cleanup.captured(user_id, scenario, &vars);
+            }
+            requests.push(result);
+        }
+
+        if let Some(scenario) = tracked.filter(|_| cleaned) {
+            self.cleanup.cleaned(user_id, scenario);
+        }
+        requests
+    }
+
+    async fn run_file_step(
+        &self,
+        step: &StepDefinition,
+        user_id: &str,
+        rows: &mut HashMap<String, Record>,
+        vars: &mut Vars,
+        rng: &mut StdRng,
+    ) -> RequestResult {
+        // Sending `{{name}}` literally would hit the wrong path, or delete the wrong thing
+        let missing = step
+            .variables()
+            .into_iter()
+            .find(|name| !vars.contains_key(*name));
+        if let Some(name) = missing.filter(|_| !self.dry_run) {
+            return self.skip(step, user_id, name);
+        }
+
+        let mut request = self.request(
+            &step.name,
+            &step.service,
+            &step.method,
+            String::new(),
+            user_id,
+            rng,
+        );
+        // Each feeder is drawn from once per scenario, so every step sees the same row
+        let mut lookup = |expr: &Expr| match *expr {
+            Expr::Feeder(feeder, column) => {
+                if !rows.contains_key(feeder) {
+                    rows.insert(feeder.to_string(), self.draw(feeder, user_id, rng)?);
+                }
+                rows[feeder].get(column).cloned().map(Into::into)
+            }
+            _ => template::resolve(expr, user_id, vars, rng),
+        };
+
+        let base = self.endpoints.get(&step.service).unwrap_or_default();
+        request.url = format!("{}{}", base, template::render(&step.path, &mut lookup));
+        for (name, value) in &step.headers {
+            request.header(name, template::render(value, &mut lookup));
+        }
+        request.body = step
+            .body
+            .as_ref()
+            .map(|body| template::render_json(body, &mut lookup));
+        request.expect = step.expect_status.map(Expect::Status);
+
+        let (mut result, body) = self.send(request).await;
+        if result.success && !self.dry_run {
+            self.capture(step, &mut result, body.as_deref(), vars);
+        }
+        result
+    }
+
+    /// A failed result for a step that was not sent because `{{missing}}` was never captured
+    fn skip(&self, step: &StepDefinition, user_id: &str, missing: &str) -> RequestResult {
+        let url = format!(
+            "{}{}",
+            self.endpoints.get(&step.service).unwrap_or_default(),
+            step.path
+        );
+        let error = format!("not sent: {{{{{}}}}} was never captured", missing);
+        if self.verbose {
+            println!(
+                "{}",
+                format!("[{}] {} {} - SKIPPED: {}", user_id, step.method, url, error).yellow()
+            );
+        }
+        RequestResult {
+            step: step.name.clone(),
+            method: step.method.clone(),
+            url,
+            user_id: user_id.to_string(),
+            success: false,
+            response_time: Duration::ZERO,
+            status: 0,
+            error: Some(error),
+            error_kind: Some(ErrorKind::Skipped),
+            retries: Vec::new(),
+            limiter_wait: Duration::ZERO,
+            started_at: Instant::now().duration_since(self.started),
+            connect_time: Duration::ZERO,
+        }
+    }
+
+    /// Store the step's captures in `vars`; a missing one fails the step
+    fn capture(
+        &self,
+        step: &StepDefinition,
+        result: &mut RequestResult,
+        body: Option<&str>,
+        vars: &mut Vars,
+    ) {
+        for (name, pointer) in &step.capture {
+            match body.and_then(|body| template::capture(body, pointer)) {
+                Some(value) => {
+                    vars.insert(name.clone(), value);
+                }
+                None => {
+                    result.success = false;
+                    result.error_kind = Some(ErrorKind::Assertion);
+                    result.error = Some(format!(
+                        "could not capture '{}': nothing at {} in the response",
+                        name, pointer
+                    ));
+                    return;
+                }
+            }
+        }
+    }
+
+    /// A row from feeder `name`; a sequential or unique feeder that has run out
+    /// ends the run early, and its rows are reused by scenarios already under way
+    fn draw(&self, name: &str, user_id: &str, rng: &mut StdRng) -> Option<Record> {
+        let (row, ran_out) = self.feeders.draw(name, user_id, rng)?;
+        if ran_out && !self.stop.is_stopped() && !self.cleaning.load(Ordering::Relaxed) {
+            let reason = format!("feeder '{}' ran out of rows", name);
+            println!(
+                "{}",
+                format!(
+                    "🛑 {} - no new scenarios will start, waiting for in-flight ones",
+                    reason
+                )
+                .red()
+                .bold()
+            );
+            self.stop.exhaust(reason);
+        }
+        Some(row.clone())
+    }
+
+    /// A food id from the catalog this user listed, or from the `food_ids` feeder
+    /// when the catalog could not be read
+    fn pick_food(
+        &self,
+        list_foods: &RequestResult,
+        body: Option<String>,
+        user_id: &str,
+        rng: &mut StdRng,
+    ) -> String {
+        let foods = body
+            .filter(|_| list_foods.success)
+            .map(|body| food_catalog::parse_catalog(&body))
+            .unwrap_or_default();
+        if let Some(food) = self.food_catalog.pick(&foods, rng) {
+            self.catalog_usage.catalog_pick();
+            return food.id.clone();
+        }
+
+        if !self.dry_run {
+            let reason = if !list_foods.success {
+                "list_foods failed"
+            } else {
+                "no food ids in the list_foods response"
+            };
+            self.catalog_usage.fallback_pick(reason);
+        }
+        self.feed("food_ids", "food_id", user_id, rng)
+    }
+
+    /// One value of a built-in PetSite feeder
+    fn feed(&self, feeder: &str, column: &str, user_id: &str, rng: &mut StdRng) -> String {
+        self.draw(feeder, user_id, rng)
+            .and_then(|mut row| row.remove(column))
+            .unwrap_or_default()
+    }
+
+    async fn run_petsite_steps(&self, user_id: &str, rng: &mut StdRng) -> Vec<RequestResult> {
+        let mut requests = Vec::new();
+        let mut changes = UserChanges::default();
+        self.run_petsite_journey(user_id, &mut requests, &mut changes, rng)
+            .await;
+
+        // Step 7: Cleanup operations; after an interrupt only what this user changed is cleaned up
+        let interrupted = self.stop.is_interrupted();
+        let cleanup_requests = self
+            .run_petsite_cleanup(
+                user_id,
+                !interrupted || changes.cart,
+                !interrupted || changes.adoptions,
+                rng,
+            )
+            .await;
+        if let Some(scenario) = changes
+            .tracked
+            .filter(|_| cleanup_requests.iter().all(|r| r.success))
+        {
+            self.cleanup.cleaned(user_id, scenario);
+        }
+        requests.extend(cleanup_requests);
+
+        requests
+    }
+
+    async fn run_petsite_cleanup(
+        &self,
+        user_id: &str,
+        empty_cart: bool,
+        cleanup_adoptions: bool,
+        rng: &mut StdRng,
+    ) -> Vec<RequestResult> {
+        let vars = Vars::new();
+        let mut requests = Vec::new();
+        let payforadoption = self
+            .endpoints
+            .get(services::PAYFORADOPTION)
+            .unwrap_or_default();
+        let petfood = self.endpoints.get(services::PETFOOD).unwrap_or_default();
+        let petfood_base = petfood.replace("/api/foods", "");
+
+        // Empty the cart
+        if empty_cart {
+            let empty_cart_url = format!(
+                "{}{}",
+                petfood_base,
+                self.render("/api/cart/{{user_id}}", user_id, &vars, rng)
+            );
+            let empty_cart_result = self
+                .make_request(
+                    "empty_cart",
+                    services::PETFOOD,
+                    "DELETE",
+                    &empty_cart_url,
+                    user_id,
+                    rng,
+                )
+                .await;
+            requests.push(empty_cart_result);
+        }
+
+        // Clean up all adoptions for this user (single DELETE operation)
+        if cleanup_adoptions {
+            let cleanup_adoption_url = payforadoption.replace(
+                "/api/completeadoption",
+                &self.render("/api/cleanupadoptions/{{user_id}}", user_id, &vars, rng),
+            );
+            let cleanup_adoption_result = self
+                .make_request(
+                    "cleanup_adoptions",
+                    services::PAYFORADOPTION,
+                    "DELETE",
+                    &cleanup_adoption_url,
+                    user_id,
+                    rng,
+                )
+                .await;
+            requests.push(cleanup_adoption_result);
+        }
+
+        requests
+    }
+
+    /// Steps 1-6 of the PetSite scenario; returns early when the run is interrupted
+    async fn run_petsite_journey(
+        &self,
+        user_id: &str,
+        requests: &mut Vec<RequestResult>,
+        changes: &mut UserChanges,
+        rng: &mut StdRng,
+    ) {
+        let petsearch = self.endpoints.get(services::PETSEARCH).unwrap_or_default();
+        let payforadoption = self
+            .endpoints
+            .get(services::PAYFORADOPTION)
+            .unwrap_or_default();
+        let petlistadoptions = self
+            .endpoints
+            .get(services::PETLISTADOPTIONS)
+            .unwrap_or_default();
+        let petfood = self.endpoints.get(services::PETFOOD).unwrap_or_default();
+        // Ids picked from earlier responses, for the templates below
+        let mut vars = Vars::new();
 
         // Step 1: List all pets via petsearch
         let list_all_pets_result = self
-            .make_request("GET", &self.endpoints.petsearch, &user_id, None::<()>)
+            .make_request(
+                "list_pets",
+                services::PETSEARCH,
+                "GET",
+                petsearch,
+                user_id,
+                rng,
+            )
             .await;
         requests.push(list_all_pets_result.clone());
+        if self.stop.is_interrupted() {
+            return;
+        }
 
-        // Step 2: Filter by color (random selection)
-        let colors = ["black", "brown", "purple", "red", "blue"]; // Include some invalid colors
-        let mut rng = rand::thread_rng();
-        let random_color = colors[rng.gen_range(0..colors.len())];
-        let color_search_url = if self.endpoints.petsearch.ends_with('?') {
-            format!("{}petcolor={}", self.endpoints.petsearch, random_color)
-        } else {
-            format!("{}?petcolor={}", self.endpoints.petsearch, random_color)
-        };
+        // Step 2: Filter by color (from the colors feeder)
+        let random_color = self.feed("colors", "color", user_id, rng);
+        let color_search_url = with_query(petsearch, &[("petcolor", &random_color)]);
         let color_search_result = self
-            .make_request("GET", &color_search_url, &user_id, None::<()>)
+            .make_request(
+                "search_by_color",
+                services::PETSEARCH,
+                "GET",
+                &color_search_url,
+                user_id,
+                rng,
+            )
             .await;
         requests.push(color_search_result);
 
-        // Step 3: Filter by pet type (random selection)
-        let pet_types = ["puppy", "kitten", "bunny"];
-        let random_pet_type = pet_types[rng.gen_range(0..pet_types.len())];
-        let type_search_url = if self.endpoints.petsearch.ends_with('?') {
-            format!("{}pettype={}", self.endpoints.petsearch, random_pet_type)
-        } else {
-            format!("{}?pettype={}", self.endpoints.petsearch, random_pet_type)
-        };
+        // Step 3: Filter by pet type (from the pet_types feeder)
+        let random_pet_type = self.feed("pet_types", "pet_type", user_id, rng);
+        let type_search_url = with_query(petsearch, &[("pettype", &random_pet_type)]);
         let type_search_result = self
-            .make_request("GET", &type_search_url, &user_id, None::<()>)
+            .make_request(
+                "search_by_type",
+                services::PETSEARCH,
+                "GET",
+                &type_search_url,
+                user_id,
+                rng,
+            )
             .await;
         requests.push(type_search_result);
 
@@ -366,50 +999,60 @@ impl LoadTester {
         let mut adopted_pets = Vec::new();
 
         for pet_type in &pet_types_to_adopt {
+            if self.stop.is_interrupted() {
+                return;
+            }
             // Search for specific pet type
-            let specific_search_url = if self.endpoints.petsearch.ends_with('?') {
-                format!("{}pettype={}", self.endpoints.petsearch, pet_type)
-            } else {
-                format!("{}?pettype={}", self.endpoints.petsearch, pet_type)
-            };
-            let specific_search_result = self
-                .make_request("GET", &specific_search_url, &user_id, None::<()>)
+            let specific_search_url = with_query(petsearch, &[("pettype", pet_type)]);
+            let step = format!("search_{}", pet_type);
+            let (specific_search_result, body) = self
+                .send(self.request(
+                    &step,
+                    services::PETSEARCH,
+                    "GET",
+                    specific_search_url,
+                    user_id,
+                    rng,
+                ))
                 .await;
-            requests.push(specific_search_result.clone());
-
-            // Select a pet of this type for adoption
-            let selected_pet_id = if specific_search_result.success {
-                // Try to parse the response to get pets of this type
-                if let Ok(response) = self.client.get(&specific_search_url).send().await {
-                    if let Ok(text) = response.text().await {
-                        if let Ok(pets) = serde_json::from_str::<PetListResponse>(&text) {
-                            if !pets.is_empty() {
-                                let random_pet = &pets[rng.gen_range(0..pets.len())];
-                                random_pet.petid.clone()
-                            } else {
-                                format!("{}_{:03}", pet_type, rng.gen_range(1..1000))
-                            }
-                        } else {
-                            format!("{}_{:03}", pet_type, rng.gen_range(1..1000))
-                        }
-                    } else {
-                        format!("{}_{:03}", pet_type, rng.gen_range(1..1000))
-                    }
-                } else {
-                    format!("{}_{:03}", pet_type, rng.gen_range(1..1000))
-                }
-            } else {
-                success = false;
-                format!("{}_{:03}", pet_type, rng.gen_range(1..1000))
-            };
+            let success = specific_search_result.success;
+            requests.push(specific_search_result);
+
+            // Adopt a random pet from the search results, or a made-up id when
+            // the search failed or returned nothing usable
+            let selected_pet_id = body
+                .filter(|_| success)
+                .and_then(|body| serde_json::from_str::<PetListResponse>(&body).ok())
+                .filter(|pets| !pets.is_empty())
+                .map(|pets| pets[rng.gen_range(0..pets.len())].petid.clone())
+                .unwrap_or_else(|| format!("{}_{:03}", pet_type, rng.gen_range(1..1000)));
+            vars.insert("pet_id".to_string(), selected_pet_id.clone().into());
+            vars.insert("pet_type".to_string(), pet_type.to_string().into());
 
             // Pay for adoption of this pet
+            if changes.tracked.is_none() {
+                changes.tracked = Some(self.cleanup.touched(user_id));
+            }
+            changes.adoptions = true;
             let adoption_url = format!(
-                "{}?petId={}&petType={}&userId={}",
-                self.endpoints.payforadoption, selected_pet_id, pet_type, user_id
+                "{}{}",
+                payforadoption,
+                self.render(
+                    "?petId={{pet_id}}&petType={{pet_type}}&userId={{user_id}}",
+                    user_id,
+                    &vars,
+                    rng
+                )
             );
             let adoption_result = self
-                .make_request("POST", &adoption_url, &user_id, None::<()>)
+                .make_request(
+                    &format!("adopt_{}", pet_type),
+                    services::PAYFORADOPTION,
+                    "POST",
+                    &adoption_url,
+                    user_id,
+                    rng,
+                )
                 .await;
             requests.push(adoption_result);
 
@@ -417,254 +1060,534 @@ impl LoadTester {
             adopted_pets.push((selected_pet_id, pet_type.to_string()));
         }
 
+        if self.stop.is_interrupted() {
+            return;
+        }
+
         // Step 5: Check adoptions list (verify adoptions were recorded)
         let adoptions_check_result = self
             .make_request(
+                "list_adoptions",
+                services::PETLISTADOPTIONS,
                 "GET",
-                &self.endpoints.petlistadoptions,
-                &user_id,
-                None::<()>,
+                petlistadoptions,
+                user_id,
+                rng,
             )
             .await;
         requests.push(adoptions_check_result);
 
         // Step 6: Comprehensive Pet Food Testing
-        let petfood_base = self.endpoints.petfood.replace("/api/foods", "");
+        let petfood_base = petfood.replace("/api/foods", "");
 
-        // 6.1: List all foods
-        let food_list_result = self
-            .make_request("GET", &self.endpoints.petfood, &user_id, None::<()>)
+        // 6.1: List all foods, keeping the catalog to pick a food from
+        let (food_list_result, food_list_body) = self
+            .send(self.request(
+                "list_foods",
+                services::PETFOOD,
+                "GET",
+                petfood.to_string(),
+                user_id,
+                rng,
+            ))
             .await;
+        let random_food_id = self.pick_food(&food_list_result, food_list_body, user_id, rng);
+        vars.insert("food_id".to_string(), random_food_id.into());
         requests.push(food_list_result);
 
-        // 6.2: Search foods with filters (random combinations)
-        let pet_types_food = ["puppy", "kitten", "bunny"];
-        let max_prices = ["10", "25", "50", "100"];
-        let search_terms = ["royal", "premium", "organic", "chicken"];
-
-        let random_pet_type_food = pet_types_food[rng.gen_range(0..pet_types_food.len())];
-        let random_max_price = max_prices[rng.gen_range(0..max_prices.len())];
-        let random_search = search_terms[rng.gen_range(0..search_terms.len())];
+        // 6.2: Search foods with filters (combinations drawn from the feeders)
+        let random_pet_type_food = self.feed("pet_types", "pet_type", user_id, rng);
+        let random_max_price = self.feed("max_prices", "max_price", user_id, rng);
+        let random_search = self.feed("search_terms", "term", user_id, rng);
 
         // Filter by pet type and price
-        let filter_url = format!(
-            "{}?pettype={}&max_price={}",
-            self.endpoints.petfood, random_pet_type_food, random_max_price
+        let filter_url = with_query(
+            petfood,
+            &[
+                ("pettype", &random_pet_type_food),
+                ("max_price", &random_max_price),
+            ],
         );
         let filter_result = self
-            .make_request("GET", &filter_url, &user_id, None::<()>)
+            .make_request(
+                "filter_foods",
+                services::PETFOOD,
+                "GET",
+                &filter_url,
+                user_id,
+                rng,
+            )
             .await;
         requests.push(filter_result);
 
         // Search by term
-        let search_url = format!("{}?search={}", self.endpoints.petfood, random_search);
+        let search_url = with_query(petfood, &[("search", &random_search)]);
         let search_result = self
-            .make_request("GET", &search_url, &user_id, None::<()>)
+            .make_request(
+                "search_foods",
+                services::PETFOOD,
+                "GET",
+                &search_url,
+                user_id,
+                rng,
+            )
             .await;
         requests.push(search_result);
 
-        // 6.3: Get specific food by ID (simulate getting a food ID from previous responses)
-        let food_ids = [
-            "F046a4eca",
-            "Fecd30d31",
-            "F36a222eb",
-            "Fc7f447a1",
-            "F233c473c",
-            "Ffb5ef0e2",
-        ];
-        let random_food_id = food_ids[rng.gen_range(0..food_ids.len())];
-        let food_detail_url = format!("{}/{}", self.endpoints.petfood, random_food_id);
+        // 6.3: Get the food picked from the catalog
+        let food_detail_url = format!(
+            "{}{}",
+            petfood,
+            self.render("/{{food_id}}", user_id, &vars, rng)
+        );
         let food_detail_result = self
-            .make_request("GET", &food_detail_url, &user_id, None::<()>)
+            .make_request(
+                "food_detail",
+                services::PETFOOD,
+                "GET",
+                &food_detail_url,
+                user_id,
+                rng,
+            )
             .await;
         requests.push(food_detail_result);
 
+        if self.stop.is_interrupted() {
+            return;
+        }
+
         // 6.4: Cart operations
         // List current cart
-        let cart_list_url = format!("{}/api/cart/{}", petfood_base, user_id);
+        let cart_list_url = format!(
+            "{}{}",
+            petfood_base,
+            self.render("/api/cart/{{user_id}}", user_id, &vars, rng)
+        );
         let cart_list_result = self
-            .make_request("GET", &cart_list_url, &user_id, None::<()>)
+            .make_request(
+                "view_cart",
+                services::PETFOOD,
+                "GET",
+                &cart_list_url,
+                user_id,
+                rng,
+            )
             .await;
         requests.push(cart_list_result);
 
+        if self.stop.is_interrupted() {
+            return;
+        }
+
         // Add item to cart
-        let add_to_cart_url = format!("{}/api/cart/{}/items", petfood_base, user_id);
+        if changes.tracked.is_none() {
+            changes.tracked = Some(self.cleanup.touched(user_id));
+        }
+        changes.cart = true;
+        let add_to_cart_url = format!(
+            "{}{}",
+            petfood_base,
+            self.render("/api/cart/{{user_id}}/items", user_id, &vars, rng)
+        );
         let add_cart_payload = serde_json::json!({
-            "food_id": random_food_id,
-            "quantity": rng.gen_range(1..5)
+            "food_id": "{{food_id}}",
+            "quantity": "{{random_int 1 4}}"
         });
-        let add_cart_result = self
-            .make_request("POST", &add_to_cart_url, &user_id, Some(add_cart_payload))
+        let (add_cart_result, _) = self
+            .send(Request {
+                body: Some(self.render_json(&add_cart_payload, user_id, &vars, rng)),
+                ..self.request(
+                    "add_to_cart",
+                    services::PETFOOD,
+                    "POST",
+                    add_to_cart_url,
+                    user_id,
+                    rng,
+                )
+            })
             .await;
         requests.push(add_cart_result);
 
         // Update item quantity in cart
         let update_cart_url = format!(
-            "{}/api/cart/{}/items/{}",
-            petfood_base, user_id, random_food_id
+            "{}{}",
+            petfood_base,
+            self.render(
+                "/api/cart/{{user_id}}/items/{{food_id}}",
+                user_id,
+                &vars,
+                rng
+            )
         );
         let update_cart_payload = serde_json::json!({
-            "quantity": rng.gen_range(1..10)
+            "quantity": "{{random_int 1 9}}"
         });
-        let update_cart_result = self
-            .make_request("PUT", &update_cart_url, &user_id, Some(update_cart_payload))
+        let (update_cart_result, _) = self
+            .send(Request {
+                body: Some(self.render_json(&update_cart_payload, user_id, &vars, rng)),
+                ..self.request(
+                    "update_cart",
+                    services::PETFOOD,
+                    "PUT",
+                    update_cart_url,
+                    user_id,
+                    rng,
+                )
+            })
             .await;
         requests.push(update_cart_result);
 
+        if self.stop.is_interrupted() {
+            return;
+        }
+
         // 6.5: Checkout process
-        let checkout_url = format!("{}/api/cart/{}/checkout", petfood_base, user_id);
-        let checkout_payload = serde_json::json!({
-            "payment_method": {
-                "CreditCard": {
-                    "card_number": "4111111111111111",
-                    "expiry_month": 12,
-                    "expiry_year": 2025,
-                    "cvv": "123",
-                    "cardholder_name": format!("User {}", user_id)
-                }
-            },
-            "shipping_address": {
-                "name": format!("User {}", user_id),
-                "street": "123 Main St",
-                "city": "Seattle",
-                "state": "WA",
-                "zip_code": "98101",
-                "country": "USA"
-            },
-            "billing_address": {
-                "name": format!("User {}", user_id),
-                "street": "123 Main St",
-                "city": "Seattle",
-                "state": "WA",
-                "zip_code": "98101",
-                "country": "USA"
-            }
-        });
-        let checkout_result = self
-            .make_request("POST", &checkout_url, &user_id, Some(checkout_payload))
+        let checkout_url = format!(
+            "{}{}",
+            petfood_base,
+            self.render("/api/cart/{{user_id}}/checkout", user_id, &vars, rng)
+        );
+        let (checkout_payload, invalid_payment) = self.payment.checkout(rng);
+        let step = invalid_payment.map_or("checkout", |invalid| invalid.step());
+        // An invalid payment passes when the service rejects it
+        let (checkout_result, _) = self
+            .send(Request {
+                body: Some(checkout_payload),
+                expect: invalid_payment.map(|_| Expect::ClientError),
+                ..self.request(step, services::PETFOOD, "POST", checkout_url, user_id, rng)
+            })
             .await;
         requests.push(checkout_result);
+    }
 
-        // Step 7: Cleanup operations
-        // Empty the cart
-        let empty_cart_url = format!("{}/api/cart/{}", petfood_base, user_id);
-        let empty_cart_result = self
-            .make_request("DELETE", &empty_cart_url, &user_id, None::<()>)
-            .await;
-        requests.push(empty_cart_result);
-
-        // Clean up all adoptions for this user (single DELETE operation)
-        let cleanup_adoption_url = self.endpoints.payforadoption.replace(
-            "/api/completeadoption",
-            &format!("/api/cleanupadoptions/{}", user_id),
-        );
-        let cleanup_adoption_result = self
-            .make_request("DELETE", &cleanup_adoption_url, &user_id, None::<()>)
-            .await;
-        requests.push(cleanup_adoption_result);
+    /// Render a built-in scenario template with the ids picked so far in `vars`
+    fn render(&self, text: &str, user_id: &str, vars: &Vars, rng: &mut StdRng) -> String {
+        template::render(text, &mut |expr| {
+            template::resolve(expr, user_id, vars, rng)
+        })
+    }
 
-        // Check if any request failed
-        success = requests.iter().all(|r| r.success);
-        if !success {
-            error = Some("One or more requests failed".to_string());
-        }
+    /// `render` for every string in a JSON body
+    fn render_json(
+        &self,
+        body: &serde_json::Value,
+        user_id: &str,
+        vars: &Vars,
+        rng: &mut StdRng,
+    ) -> serde_json::Value {
+        template::render_json(body, &mut |expr| {
+            template::resolve(expr, user_id, vars, rng)
+        })
+    }
 
-        UserScenarioResult {
+    /// A request without a body, carrying the configured headers
+    fn request<'a>(
+        &self,
+        step: &'a str,
+        service: &'a str,
+        method: &'a str,
+        url: String,
+        user_id: &'a str,
+        rng: &mut StdRng,
+    ) -> Request<'a> {
+        let headers = self
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                let value = template::render(value, &mut |expr| expr.function(user_id, rng));
+                (name.clone(), value)
+            })
+            .collect();
+        Request {
+            step,
+            service,
+            method,
+            url,
             user_id,
-            requests,
-            total_time: start_time.elapsed(),
-            success,
-            error,
+            headers,
+            body: None,
+            expect: None,
         }
     }
 
-    async fn make_request<T: serde::Serialize>(
+    async fn make_request(
         &self,
+        step: &str,
+        service: &str,
         method: &str,
         url: &str,
         user_id: &str,
-        data: Option<T>,
+        rng: &mut StdRng,
     ) -> RequestResult {
+        self.send(self.request(step, service, method, url.to_string(), user_id, rng))
+            .await
+            .0
+    }
+
+    /// Send a request, also returning the response body when one was read.
+    /// Failed attempts are retried as the step's retry policy allows, and the
+    /// final response is checked against `request.expect` when one is given.
+    async fn send(&self, request: Request<'_>) -> (RequestResult, Option<String>) {
+        let Request {
+            step,
+            service,
+            method,
+            user_id,
+            ..
+        } = request;
+        let url = request.url.as_str();
+        let policy = self.retry.policy(step);
+        let started_at = Instant::now().duration_since(self.started);
+        let mut retries = Vec::new();
+        let mut limiter_wait = Duration::ZERO;
+        let mut attempt = 1;
+
+        loop {
+            if !self.dry_run {
+                limiter_wait += self.limiter.acquire(service).await;
+            }
+            let (mut result, body) = self.attempt(&request).await;
+            if result.success
+                || self.stop.is_interrupted()
+                || !policy.should_retry(&result, attempt)
+            {
+                result.started_at = started_at;
+                result.retries = retries;
+                result.limiter_wait = limiter_wait;
+                if let Some(expect) = request.expect {
+                    self.check_status(&mut result, expect);
+                }
+                self.breaker.record(&result);
+                return (result, body);
+            }
+
+            let kind = result.failure_kind().unwrap_or(ErrorKind::Other);
+            let backoff = policy.backoff(attempt);
+            if self.verbose {
+                println!(
+                    "{}",
+                    format!(
+                        "[{}] ↻ Retrying {} {} after {} in {}ms (attempt {}/{})",
+                        user_id,
+                        method,
+                        url,
+                        kind,
+                        backoff.as_millis(),
+                        attempt + 1,
+                        policy.max_attempts
+                    )
+                    .yellow()
+                );
+            }
+            retries.push(kind);
+            tokio::time::sleep(backoff).await;
+            attempt += 1;
+        }
+    }
+
+    /// Mark a response with a status other than the expected one as an assertion failure
+    fn check_status(&self, result: &mut RequestResult, expect: Expect) {
+        // Only responses can be checked; transport errors keep their kind
+        if result.status == 0 || self.dry_run {
+            return;
+        }
+        result.success = match expect {
+            Expect::Status(expected) => result.status == expected,
+            Expect::ClientError => (400..500).contains(&result.status),
+        };
+        if result.success {
+            result.error_kind = None;
+            result.error = None;
+        } else {
+            result.error_kind = Some(ErrorKind::Assertion);
+            result.error = Some(match expect {
+                Expect::Status(expected) => {
+                    format!("expected status {}, got {}", expected, result.status)
+                }
+                Expect::ClientError => format!("expected a 4xx status, got {}", result.status),
+            });
+        }
+    }
+
+    /// Send a request and read its response, following redirects by hand so the
+    /// cookies each hop sets land in the user's jar
+    async fn exchange(
+        &self,
+        request: &Request<'_>,
+        mut body: Option<Vec<u8>>,
+        mut auth: Vec<(String, String)>,
+    ) -> Result<(u16, String), (ErrorKind, String)> {
+        let session = self
+            .sessions
+            .get(request.user_id)
+            .map_err(|err| (ErrorKind::Other, err.to_string()))?;
+        let mut method = match request.method {
+            "GET" => Method::GET,
+            "POST" => Method::POST,
+            "PUT" => Method::PUT,
+            "DELETE" => Method::DELETE,
+            other => {
+                return Err((
+                    ErrorKind::Other,
+                    format!("Unsupported HTTP method: {}", other),
+                ))
+            }
+        };
+        let mut url = Url::parse(&request.url)
+            .map_err(|err| (ErrorKind::Other, format!("Invalid URL: {}", err)))?;
+
+        for _ in 0..=MAX_REDIRECTS {
+            let mut request_builder = session.client.request(method.clone(), url.clone());
+            for (name, value) in request.headers.iter().chain(&auth) {
+                request_builder = request_builder.header(name, value);
+            }
+            if let Some(cookie) = session.cookies.as_ref().and_then(|jar| jar.cookies(&url)) {
+                request_builder = request_builder.header(COOKIE, cookie);
+            }
+            if let Some(body) = &body {
+                request_builder = request_builder
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(body.clone());
+            }
+
+            self.sessions.sent();
+            let response = request_builder
+                .send()
+                .await
+                .map_err(|err| (ErrorKind::from_reqwest(&err), err.to_string()))?;
+            if let Some(jar) = &session.cookies {
+                jar.set_cookies(&mut response.headers().get_all(SET_COOKIE).iter(), &url);
+            }
+            let status = response.status();
+            let location = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .and_then(|location| url.join(location).ok())
+                .filter(|_| status.is_redirection());
+
+            let Some(next) = location else {
+                // Read the whole body to complete the request
+                let text = response
+                    .text()
+                    .await
+                    .map_err(|err| (ErrorKind::BodyRead, err.to_string()))?;
+                return Ok((status.as_u16(), text));
+            };
+
+            // Like browsers: 303, and 301/302 after a POST, continue as a GET without the body
+            if status == StatusCode::SEE_OTHER
+                || (matches!(status, StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND)
+                    && method == Method::POST)
+            {
+                method = Method::GET;
+                body = None;
+            }
+            // Credentials only follow redirects within the same origin
+            auth = if next.origin() == url.origin() {
+                self.auth
+                    .headers(
+                        request.service,
+                        method.as_str(),
+                        next.as_str(),
+                        body.as_deref(),
+                    )
+                    .await
+                    .map_err(|message| (ErrorKind::Auth, message))?
+            } else {
+                Vec::new()
+            };
+            url = next;
+        }
+        Err((
+            ErrorKind::Other,
+            format!("more than {} redirects", MAX_REDIRECTS),
+        ))
+    }
+
+    /// Send the request once
+    async fn attempt(&self, request: &Request<'_>) -> (RequestResult, Option<String>) {
+        let Request {
+            step,
+            service,
+            method,
+            user_id,
+            ..
+        } = *request;
+        let url = request.url.as_str();
+        let body = match method {
+            "POST" | "PUT" => request
+                .body
+                .as_ref()
+                .map(|body| serde_json::to_vec(body).unwrap_or_default()),
+            _ => None,
+        };
+        // Credentials come before the timer starts, so token requests don't count as latency
+        let auth = if self.dry_run {
+            Ok(Vec::new())
+        } else {
+            self.auth
+                .headers(service, method, url, body.as_deref())
+                .await
+        };
         let start_time = Instant::now();
+        let mut result = RequestResult {
+            step: step.to_string(),
+            method: method.to_string(),
+            url: url.to_string(),
+            user_id: user_id.to_string(),
+            success: false,
+            response_time: Duration::ZERO,
+            status: 0,
+            error: None,
+            error_kind: None,
+            retries: Vec::new(),
+            limiter_wait: Duration::ZERO,
+            started_at: start_time.duration_since(self.started),
+            connect_time: Duration::ZERO,
+        };
 
         if self.dry_run {
             println!(
                 "{}",
                 format!("[DRY RUN] {} {} ({})", method, url, user_id).purple()
             );
-            return RequestResult {
-                method: method.to_string(),
-                url: url.to_string(),
-                user_id: user_id.to_string(),
-                success: true,
-                response_time: Duration::from_millis(0),
-                status: 200,
-                error: None,
-            };
+            result.success = true;
+            result.status = 200;
+            return (result, None);
         }
 
         let request_future = async {
-            let mut request_builder = match method {
-                "GET" => self.client.get(url),
-                "POST" => {
-                    let mut builder = self.client.post(url);
-                    if let Some(payload) = data {
-                        builder = builder.json(&payload);
-                    }
-                    builder
-                }
-                "PUT" => {
-                    let mut builder = self.client.put(url);
-                    if let Some(payload) = data {
-                        builder = builder.json(&payload);
-                    }
-                    builder
-                }
-                "DELETE" => self.client.delete(url),
-                _ => return Err(anyhow::anyhow!("Unsupported HTTP method: {}", method)),
-            };
-
-            request_builder =
-                request_builder.header("User-Agent", format!("LoadTester-{}", user_id));
-
-            let response = request_builder.send().await?;
-            let status = response.status().as_u16();
-
-            // Consume the response body to complete the request
-            let _body = response.text().await?;
-
-            Ok(status)
+            let auth = auth.map_err(|message| (ErrorKind::Auth, message))?;
+            self.exchange(request, body, auth).await
         };
 
-        let result = match timeout(Duration::from_secs(10), request_future).await {
-            Ok(Ok(status)) => RequestResult {
-                method: method.to_string(),
-                url: url.to_string(),
-                user_id: user_id.to_string(),
-                success: status >= 200 && status < 400,
-                response_time: start_time.elapsed(),
-                status,
-                error: None,
-            },
-            Ok(Err(err)) => RequestResult {
-                method: method.to_string(),
-                url: url.to_string(),
-                user_id: user_id.to_string(),
-                success: false,
-                response_time: start_time.elapsed(),
-                status: 0,
-                error: Some(err.to_string()),
-            },
-            Err(_) => RequestResult {
-                method: method.to_string(),
-                url: url.to_string(),
-                user_id: user_id.to_string(),
-                success: false,
-                response_time: start_time.elapsed(),
-                status: 0,
-                error: Some("Request timeout".to_string()),
-            },
-        };
+        let mut body = None;
+        let (outcome, connect_time) =
+            session::timed_connects(timeout(self.request_timeout, request_future)).await;
+        match outcome {
+            Ok(Ok((status, text))) => {
+                if status == 401 {
+                    self.auth.rejected(service).await;
+                }
+                result.status = status;
+                result.success = (200..400).contains(&status);
+                if !result.success {
+                    result.error_kind = Some(ErrorKind::Http(status));
+                }
+                body = Some(text);
+            }
+            Ok(Err((kind, message))) => {
+                result.error_kind = Some(kind);
+                result.error = Some(message);
+            }
+            Err(_) => {
+                result.error_kind = Some(ErrorKind::RequestTimeout);
+                result.error = Some("Request timeout".to_string());
+            }
+        }
+        result.connect_time = connect_time;
+        result.response_time = start_time.elapsed();
 
         // Verbose logging
         if self.verbose {
@@ -682,6 +1605,11 @@ impl LoadTester {
                     .green()
                 );
             } else {
+                let kind = result.failure_kind().unwrap_or(ErrorKind::Other);
+                let reason = match &result.error {
+                    Some(error) => format!("{}: {}", kind, error),
+                    None => kind.to_string(),
+                };
                 println!(
                     "{}",
                     format!(
@@ -689,7 +1617,7 @@ impl LoadTester {
                         user_id,
                         method,
                         url,
-                        result.error.as_deref().unwrap_or("Unknown error"),
+                        reason,
                         result.response_time.as_millis()
                     )
                     .red()
@@ -697,212 +1625,18 @@ impl LoadTester {
             }
         }
 
-        result
+        (result, body)
     }
+}
 
-    pub fn display_results(&self, results: &[UserScenarioResult], total_time: Duration) {
-        let all_requests: Vec<&RequestResult> = results.iter().flat_map(|r| &r.requests).collect();
-
-        let total_requests = all_requests.len();
-        let successful_requests = all_requests.iter().filter(|r| r.success).count();
-        let failed_requests = total_requests - successful_requests;
-
-        let successful_response_times: Vec<Duration> = all_requests
-            .iter()
-            .filter(|r| r.success)
-            .map(|r| r.response_time)
-            .collect();
-
-        let average_response_time = if !successful_response_times.is_empty() {
-            successful_response_times.iter().sum::<Duration>()
-                / successful_response_times.len() as u32
-        } else {
-            Duration::from_millis(0)
-        };
-
-        let requests_per_second = if total_time.as_secs_f64() > 0.0 {
-            total_requests as f64 / total_time.as_secs_f64()
-        } else {
-            0.0
-        };
-
-        let success_rate = if total_requests > 0 {
-            (successful_requests as f64 / total_requests as f64) * 100.0
-        } else {
-            0.0
-        };
-
-        println!("{}", "\n📊 Load Test Results".green().bold());
-        println!("{}", "═".repeat(50).purple());
-
-        println!("{}", format!("Total Scenarios: {}", results.len()).blue());
-        println!("{}", format!("Total Requests: {}", total_requests).blue());
-        println!(
-            "{}",
-            format!("✓ Successful: {}", successful_requests).green()
-        );
-        println!("{}", format!("✗ Failed: {}", failed_requests).red());
-        println!("{}", format!("Success Rate: {:.1}%", success_rate).yellow());
-        println!(
-            "{}",
-            format!(
-                "Average Response Time: {}ms",
-                average_response_time.as_millis()
-            )
-            .cyan()
-        );
-        println!(
-            "{}",
-            format!("Requests/Second: {:.1}", requests_per_second).magenta()
-        );
-        println!(
-            "{}",
-            format!("Total Test Time: {}ms", total_time.as_millis()).purple()
-        );
-
-        if self.rampup_seconds > 0 {
-            println!(
-                "{}",
-                format!("Ramp-up Period: {}s", self.rampup_seconds).purple()
-            );
-        }
-
-        // Show detailed information only in verbose mode
-        if self.verbose {
-            // Show detailed failed request information
-            let failed_request_details: Vec<&RequestResult> = all_requests
-                .iter()
-                .filter(|r| !r.success)
-                .copied()
-                .collect();
-            if !failed_request_details.is_empty() {
-                println!("{}", "\n❌ Failed Requests Details:".red().bold());
-                println!("{}", "─".repeat(80).purple());
-
-                for (i, request) in failed_request_details.iter().enumerate() {
-                    println!(
-                        "{}",
-                        format!(
-                            "{}. {} {} ({})",
-                            i + 1,
-                            request.method,
-                            request.url,
-                            request.user_id
-                        )
-                        .red()
-                    );
-
-                    if request.status > 0 {
-                        println!("{}", format!("   Status: {}", request.status).yellow());
-                    }
-
-                    if let Some(error) = &request.error {
-                        println!("{}", format!("   Error: {}", error).red());
-                    }
-
-                    println!(
-                        "{}",
-                        format!("   Response Time: {}ms", request.response_time.as_millis()).cyan()
-                    );
-                    println!();
-                }
-            }
-
-            // Show failed scenarios summary
-            let failed_scenarios: Vec<&UserScenarioResult> =
-                results.iter().filter(|r| !r.success).collect();
-            if !failed_scenarios.is_empty() {
-                println!("{}", "📋 Failed Scenarios Summary:".red().bold());
-                for scenario in failed_scenarios {
-                    let failed_count = scenario.requests.iter().filter(|r| !r.success).count();
-                    let total_count = scenario.requests.len();
-                    println!(
-                        "{}",
-                        format!(
-                            "  {}: {}/{} requests failed",
-                            scenario.user_id, failed_count, total_count
-                        )
-                        .red()
-                    );
-                }
-            }
-
-            // Show request breakdown by endpoint
-            println!("{}", "\n📈 Request Breakdown by Endpoint:".blue().bold());
-            println!("{}", "─".repeat(80).purple());
-
-            let mut endpoint_stats = std::collections::HashMap::new();
-            for request in &all_requests {
-                let endpoint = request.url.split('?').next().unwrap_or(&request.url);
-                let stats = endpoint_stats.entry(endpoint.to_string()).or_insert((0, 0));
-                if request.success {
-                    stats.0 += 1;
-                } else {
-                    stats.1 += 1;
-                }
-            }
-
-            for (endpoint, (success, failed)) in endpoint_stats {
-                let total = success + failed;
-                let success_rate = if total > 0 {
-                    (success as f64 / total as f64) * 100.0
-                } else {
-                    0.0
-                };
-                let status_color = if success_rate >= 90.0 {
-                    "green"
-                } else if success_rate >= 70.0 {
-                    "yellow"
-                } else {
-                    "red"
-                };
-
-                match status_color {
-                    "green" => println!(
-                        "{}",
-                        format!(
-                            "  ✓ {}: {}/{} ({:.1}%)",
-                            endpoint, success, total, success_rate
-                        )
-                        .green()
-                    ),
-                    "yellow" => println!(
-                        "{}",
-                        format!(
-                            "  ⚠ {}: {}/{} ({:.1}%)",
-                            endpoint, success, total, success_rate
-                        )
-                        .yellow()
-                    ),
-                    "red" => println!(
-                        "{}",
-                        format!(
-                            "  ✗ {}: {}/{} ({:.1}%)",
-                            endpoint, success, total, success_rate
-                        )
-                        .red()
-                    ),
-                    _ => println!(
-                        "{}",
-                        format!(
-                            "  • {}: {}/{} ({:.1}%)",
-                            endpoint, success, total, success_rate
-                        )
-                    ),
-                }
-            }
-        } else if failed_requests > 0 {
-            // In non-verbose mode, just show a summary of failures
-            println!(
-                "{}",
-                format!(
-                    "\n⚠️  {} requests failed. Use --verbose for detailed error information.",
-                    failed_requests
-                )
-                .yellow()
-            );
+/// `base` with the params appended to its query string, each one encoded
+fn with_query(base: &str, params: &[(&str, &str)]) -> String {
+    match Url::parse(base) {
+        Ok(mut url) => {
+            url.query_pairs_mut().extend_pairs(params);
+            url.to_string()
         }
-
-        println!("{}", format!("\n{}", "═".repeat(50)).purple());
+        // Sent as is, so the request fails and reports the bad endpoint
+        Err(_) => base.to_string(),
     }
 }
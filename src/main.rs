@@ -1,30 +1,194 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use colored::*;
-use std::time::Instant;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
+mod abort;
+mod auth;
+mod cleanup;
+mod compare;
+mod config;
+mod endpoint_provider;
+mod errors;
+mod feeders;
+mod food_catalog;
+mod html_report;
+mod junit_report;
 mod load_tester;
+mod payment;
+mod preflight;
+mod rate_limit;
+mod report;
+mod retry;
+mod scenario;
+mod seed;
+mod session;
 mod ssm_discovery;
+mod template;
+mod thresholds;
+mod timeseries;
 mod types;
+mod users;
 
+use abort::{display_abort, AbortCondition, Signals};
+use aws_config::SdkConfig;
+use cleanup::{display_cleanup, load_users};
+use compare::RegressionConfig;
+use compare::{compare_runs, display_comparison, regressions};
+use config::{parse_header, RunConfig};
+use endpoint_provider::{
+    parse_key_value, resolve_endpoints, ConfigEndpointProvider, DiscoveryMode, EndpointProvider,
+    EnvEndpointProvider, FileEndpointProvider,
+};
+use feeders::{parse_feeder, FeederConfig, Feeders};
+use food_catalog::display_food_catalog;
 use load_tester::LoadTester;
-use ssm_discovery::SSMEndpointDiscovery;
+use preflight::{display_preflight, run_preflight};
+use rate_limit::parse_service_rate;
+use report::{display_results, write_reports, OutputTarget, RunReport};
+use scenario::Scenario;
+use session::{display_connections, ConnectionMode};
+use ssm_discovery::{load_aws_config, SSMEndpointDiscovery};
+use thresholds::display_thresholds;
+use timeseries::{display_timeseries, TimeSeries};
+use types::{Endpoints, LoadTestResults};
+use users::UserIdStrategy;
+
+/// Exit code when the run completed but one or more thresholds failed
+const EXIT_THRESHOLDS_FAILED: u8 = 2;
+
+/// Exit code when thresholds passed but the run regressed against the baseline
+const EXIT_REGRESSION: u8 = 3;
+
+/// Exit code when an abort condition stopped the run early
+const EXIT_ABORTED: u8 = 4;
+
+/// Exit code when a sequential or unique feeder ran out of rows before the run finished
+const EXIT_DATA_EXHAUSTED: u8 = 5;
+
+/// Exit code when Ctrl-C or SIGTERM stopped the run (128 + SIGINT)
+const EXIT_INTERRUPTED: u8 = 130;
 
 #[derive(Parser)]
 #[command(name = "microservice-load-tester")]
 #[command(about = "High concurrent load testing CLI for microservices")]
 #[command(version = "1.0.0")]
+#[command(args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    args: Args,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run the load test (the default when no subcommand is given)
+    Run(Args),
+    /// Resolve and print service endpoints without generating load
+    Discover(Args),
+    /// Check the config and scenario files without contacting any service
+    Validate(Args),
+    /// Render a saved JSON or NDJSON report
+    Report(ReportArgs),
+    /// Show per-step differences between two saved reports
+    Compare(CompareArgs),
+    /// Remove the carts and adoptions left behind by an earlier run
+    Cleanup(CleanupArgs),
+    /// Inspect the run configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print the fully resolved configuration (defaults, file, env vars and flags)
+    Print(Args),
+}
+
+#[derive(clap::Args)]
+struct ReportArgs {
+    /// Report written by --output json=... or --output ndjson=...
+    input: PathBuf,
+
+    /// Include failed requests and the per-step breakdown
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Also re-render the report, e.g. --output html=report.html (repeatable)
+    #[arg(long = "output", value_name = "FORMAT=PATH")]
+    outputs: Vec<OutputTarget>,
+}
+
+#[derive(clap::Args)]
+struct CleanupArgs {
+    /// Saved JSON/NDJSON report, or a users file written by --cleanup-users-file
+    #[arg(long)]
+    users_from: PathBuf,
+
+    #[command(flatten)]
+    args: Args,
+}
+
+#[derive(clap::Args)]
+struct CompareArgs {
+    /// Report from the reference run
+    baseline: PathBuf,
+
+    /// Report from the run being evaluated
+    candidate: PathBuf,
+
+    /// Largest allowed p50/p95 increase and throughput drop in percent (default: 10)
+    #[arg(long)]
+    tolerance: Option<f64>,
+
+    /// Largest allowed error rate increase in percentage points (default: 1)
+    #[arg(long)]
+    max_error_rate_increase: Option<f64>,
+
+    /// Significance level for latency regressions (default: 0.05)
+    #[arg(long)]
+    alpha: Option<f64>,
+}
+
+impl CompareArgs {
+    fn regression_config(&self) -> RegressionConfig {
+        let mut config = RegressionConfig::default();
+        if let Some(tolerance) = self.tolerance {
+            config.tolerance_pct = tolerance;
+        }
+        if let Some(increase) = self.max_error_rate_increase {
+            config.max_error_rate_increase = increase;
+        }
+        if let Some(alpha) = self.alpha {
+            config.alpha = alpha;
+        }
+        config
+    }
+}
+
+/// Run options. Flags and `LOADTEST_*` environment variables override the `--config` file.
+#[derive(clap::Args)]
 struct Args {
-    /// Number of concurrent users
-    #[arg(short, long, default_value = "10")]
-    users: usize,
+    /// TOML run configuration file
+    #[arg(long, env = "LOADTEST_CONFIG")]
+    config: Option<PathBuf>,
 
-    /// Concurrent requests per user
-    #[arg(short, long, default_value = "5")]
-    concurrent: usize,
+    /// Number of concurrent users (default: 10)
+    #[arg(short, long, env = "LOADTEST_USERS")]
+    users: Option<usize>,
 
-    /// AWS region
-    #[arg(short, long, default_value = "us-east-1")]
-    region: String,
+    /// Concurrent requests per user (default: 5)
+    #[arg(short, long, env = "LOADTEST_CONCURRENT")]
+    concurrent: Option<usize>,
+
+    /// AWS region (default: us-east-1)
+    #[arg(short, long, env = "LOADTEST_REGION")]
+    region: Option<String>,
 
     /// Show what would be tested without executing
     #[arg(long)]
@@ -34,53 +198,718 @@ struct Args {
     #[arg(short, long)]
     verbose: bool,
 
-    /// Ramp-up time in seconds to gradually increase load (0 = no ramp-up)
-    #[arg(long, default_value = "0")]
-    rampup: u64,
+    /// Start the load test even if endpoint preflight checks fail
+    #[arg(long)]
+    skip_preflight: bool,
+
+    /// Ramp-up time in seconds to gradually increase load (default: 0 = no ramp-up)
+    #[arg(long, env = "LOADTEST_RAMPUP")]
+    rampup: Option<u64>,
+
+    /// TOML scenario file to run instead of the built-in PetSite scenario
+    #[arg(long, env = "LOADTEST_SCENARIO")]
+    scenario: Option<PathBuf>,
+
+    /// Where to discover endpoints from; env vars fill in anything it misses (default: ssm)
+    #[arg(long, value_enum, env = "LOADTEST_DISCOVERY")]
+    discovery: Option<DiscoveryMode>,
+
+    /// TOML file with an [endpoints] table (default for --discovery file: the --config file)
+    #[arg(long, env = "LOADTEST_ENDPOINTS_FILE")]
+    endpoints_file: Option<PathBuf>,
+
+    /// Override a service endpoint, e.g. --endpoint petsearch=http://localhost:8081 (repeatable)
+    #[arg(long = "endpoint", value_name = "SERVICE=URL", value_parser = parse_key_value)]
+    endpoints: Vec<(String, String)>,
+
+    /// Override an SSM parameter, e.g. --ssm-param petsearch=searchapiurl (repeatable).
+    /// Names without a leading '/' are relative to --ssm-prefix
+    #[arg(long = "ssm-param", value_name = "SERVICE=NAME", value_parser = parse_key_value)]
+    ssm_params: Vec<(String, String)>,
+
+    /// SSM path prefix for relative parameter names (default: /petstore/)
+    #[arg(long, env = "LOADTEST_SSM_PREFIX")]
+    ssm_prefix: Option<String>,
+
+    /// Fetch all parameters under --ssm-prefix with one GetParametersByPath call
+    #[arg(long)]
+    ssm_by_path: bool,
+
+    /// Fall back to env vars and defaults when SSM can't be read instead of failing
+    #[arg(long)]
+    ssm_fallback: bool,
+
+    /// Per-request timeout in seconds (default: 10)
+    #[arg(long, env = "LOADTEST_TIMEOUT")]
+    timeout: Option<u64>,
+
+    /// Write a report, e.g. --output json=results.json (repeatable)
+    #[arg(long = "output", value_name = "FORMAT=PATH")]
+    outputs: Vec<OutputTarget>,
+
+    /// Attempts per request, including the first, for retryable failures (default: 1 = no retries)
+    #[arg(long, env = "LOADTEST_MAX_ATTEMPTS")]
+    max_attempts: Option<u32>,
+
+    /// Cap on requests per second across all services
+    #[arg(long, env = "LOADTEST_RATE_LIMIT")]
+    rate_limit: Option<f64>,
+
+    /// Cap one service's requests per second, e.g. --service-rate-limit payforadoption=20 (repeatable)
+    #[arg(long = "service-rate-limit", value_name = "SERVICE=RPS", value_parser = parse_service_rate)]
+    service_rate_limits: Vec<(String, f64)>,
+
+    /// Time-series window in seconds, e.g. 1 or 10 (default: about 60 windows per run)
+    #[arg(long = "window", value_name = "SECONDS", env = "LOADTEST_WINDOW")]
+    window_secs: Option<u64>,
+
+    /// Saved JSON/NDJSON report to check this run against for regressions
+    #[arg(long, env = "LOADTEST_BASELINE")]
+    baseline: Option<PathBuf>,
+
+    /// Largest allowed p50/p95 increase against the baseline in percent (default: 10)
+    #[arg(long, env = "LOADTEST_REGRESSION_TOLERANCE")]
+    regression_tolerance: Option<f64>,
+
+    /// Stop the run when a live metric crosses a limit, e.g. error_rate>50@30s or p99_ms>5000@60s (repeatable)
+    #[arg(
+        long = "abort-on",
+        value_name = "METRIC>LIMIT@SECS",
+        env = "LOADTEST_ABORT_ON",
+        value_delimiter = ','
+    )]
+    abort_on: Vec<AbortCondition>,
+
+    /// Users cleaned up at the same time in the cleanup phase (default: 10)
+    #[arg(long, env = "LOADTEST_CLEANUP_CONCURRENCY")]
+    cleanup_concurrency: Option<usize>,
+
+    /// Append every user that changes state to this file as the run goes
+    #[arg(long, env = "LOADTEST_CLEANUP_USERS_FILE")]
+    cleanup_users_file: Option<PathBuf>,
+
+    /// Skip the cleanup phase at the end of the run
+    #[arg(long)]
+    no_cleanup: bool,
+
+    /// Load test data from a CSV/JSON file, e.g. --feeder search_terms=terms.csv (repeatable).
+    /// Rows are drawn at random; set a strategy under [feeders.NAME] in the config file
+    #[arg(long = "feeder", value_name = "NAME=PATH", value_parser = parse_feeder)]
+    feeders: Vec<(String, FeederConfig)>,
+
+    /// Seed for user ids and random choices; the same seed and config repeat each user's requests
+    #[arg(long, env = "LOADTEST_SEED")]
+    seed: Option<u64>,
+
+    /// Tag for this run's user ids (default: 8 random hex digits)
+    #[arg(long, env = "LOADTEST_RUN_ID")]
+    run_id: Option<String>,
+
+    /// How virtual user ids are made (default: sequence)
+    #[arg(long, value_enum, env = "LOADTEST_USER_ID_STRATEGY")]
+    user_id_strategy: Option<UserIdStrategy>,
+
+    /// Start of generated user ids (default: user)
+    #[arg(long, env = "LOADTEST_USER_ID_PREFIX")]
+    user_id_prefix: Option<String>,
+
+    /// Take user ids from this file, one per line (implies --user-id-strategy file)
+    #[arg(long, env = "LOADTEST_USER_IDS_FILE")]
+    user_ids_file: Option<PathBuf>,
+
+    /// Send a header with every request, e.g. --header "X-Request-Id: {{uuid}}" (repeatable).
+    /// Replaces a configured header of the same name
+    #[arg(long = "header", value_name = "NAME: VALUE", value_parser = parse_header)]
+    headers: Vec<(String, String)>,
+
+    /// Don't keep cookies; by default each virtual user has its own cookie jar
+    #[arg(long)]
+    no_cookies: bool,
+
+    /// How virtual users get connections: shared, per_user or per_request (default: shared)
+    #[arg(long, value_enum, env = "LOADTEST_CONNECTIONS")]
+    connections: Option<ConnectionMode>,
+
+    /// Share of checkouts sent with a deliberately invalid payment, 0 to 1 (default: 0)
+    #[arg(long, env = "LOADTEST_INVALID_PAYMENT_RATIO")]
+    invalid_payment_ratio: Option<f64>,
+}
+
+impl Args {
+    /// Layer environment variables and flags over the config file
+    fn resolve_config(&self) -> anyhow::Result<RunConfig> {
+        let mut config = RunConfig::load(self.config.as_deref())?;
+
+        // An explicit load shape replaces any stages from the file
+        if self.users.is_some() || self.concurrent.is_some() || self.rampup.is_some() {
+            config.stages.clear();
+        }
+        if let Some(users) = self.users {
+            config.users = users;
+        }
+        if let Some(concurrent) = self.concurrent {
+            config.concurrent = concurrent;
+        }
+        if let Some(rampup) = self.rampup {
+            config.rampup = rampup;
+        }
+        if let Some(region) = &self.region {
+            config.region = region.clone();
+        }
+        if let Some(scenario) = &self.scenario {
+            config.scenario = Some(scenario.clone());
+        }
+        if let Some(discovery) = self.discovery {
+            config.discovery = discovery;
+        }
+        if let Some(endpoints_file) = &self.endpoints_file {
+            config.endpoints_file = Some(endpoints_file.clone());
+        }
+        config.endpoints.extend(self.endpoints.iter().cloned());
+        for (name, feeder) in &self.feeders {
+            // Keep a strategy set for the same feeder in the config file
+            match config.feeders.get_mut(name) {
+                Some(existing) => existing.file = feeder.file.clone(),
+                None => {
+                    config.feeders.insert(name.clone(), feeder.clone());
+                }
+            }
+        }
+        config
+            .ssm
+            .parameters
+            .extend(self.ssm_params.iter().cloned());
+        if let Some(prefix) = &self.ssm_prefix {
+            config.ssm.prefix = prefix.clone();
+        }
+        if self.ssm_by_path {
+            config.ssm.by_path = true;
+        }
+        if self.ssm_fallback {
+            config.ssm.fallback = true;
+        }
+        if let Some(timeout) = self.timeout {
+            config.http.timeout_secs = timeout;
+        }
+        if self.no_cookies {
+            config.http.cookies = false;
+        }
+        if let Some(connections) = self.connections {
+            config.http.connections = connections;
+        }
+        for (name, value) in &self.headers {
+            config
+                .http
+                .headers
+                .retain(|existing, _| !existing.eq_ignore_ascii_case(name));
+            config.http.headers.insert(name.clone(), value.clone());
+        }
+        config.reporters.extend(self.outputs.iter().cloned());
+        if let Some(max_attempts) = self.max_attempts {
+            config.retry.default.max_attempts = max_attempts;
+        }
+        if let Some(rate) = self.rate_limit {
+            config.rate_limit.requests_per_second = Some(rate);
+        }
+        config
+            .rate_limit
+            .services
+            .extend(self.service_rate_limits.iter().cloned());
+        if let Some(window_secs) = self.window_secs {
+            config.window_secs = Some(window_secs);
+        }
+        config
+            .abort
+            .conditions
+            .extend(self.abort_on.iter().cloned());
+        if let Some(concurrency) = self.cleanup_concurrency {
+            config.cleanup.concurrency = concurrency;
+        }
+        if let Some(users_file) = &self.cleanup_users_file {
+            config.cleanup.users_file = Some(users_file.clone());
+        }
+        if self.no_cleanup {
+            config.cleanup.enabled = false;
+        }
+        if let Some(baseline) = &self.baseline {
+            config.baseline = Some(baseline.clone());
+        }
+        if let Some(seed) = self.seed {
+            config.seed = Some(seed);
+        }
+        if let Some(run_id) = &self.run_id {
+            config.run_id = Some(run_id.clone());
+        }
+        if let Some(file) = &self.user_ids_file {
+            config.user_ids.strategy = UserIdStrategy::File;
+            config.user_ids.file = Some(file.clone());
+        }
+        if let Some(strategy) = self.user_id_strategy {
+            config.user_ids.strategy = strategy;
+        }
+        if let Some(prefix) = &self.user_id_prefix {
+            config.user_ids.prefix = prefix.clone();
+        }
+        if let Some(ratio) = self.invalid_payment_ratio {
+            config.payment.invalid_ratio = ratio;
+        }
+        if let Some(tolerance) = self.regression_tolerance {
+            config.regression.tolerance_pct = tolerance;
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Endpoint providers in increasing order of precedence: built-in defaults < env vars
+    /// < --discovery source < the config's endpoints ([endpoints] and --endpoint overrides)
+    fn endpoint_providers(
+        &self,
+        config: &RunConfig,
+        aws: Option<&SdkConfig>,
+    ) -> anyhow::Result<Vec<Box<dyn EndpointProvider>>> {
+        let mut providers: Vec<Box<dyn EndpointProvider>> = vec![Box::new(EnvEndpointProvider)];
+        match config.discovery {
+            DiscoveryMode::Ssm => {
+                let mut parameters = SSMEndpointDiscovery::default_parameters();
+                parameters.extend(config.ssm.parameters.clone());
+                let aws =
+                    aws.ok_or_else(|| anyhow::anyhow!("SSM discovery needs the AWS config"))?;
+                let discovery = SSMEndpointDiscovery::with_custom_parameters(aws, parameters)?
+                    .with_path_prefix(&config.ssm.prefix)
+                    .with_by_path(config.ssm.by_path)
+                    .with_fallback(config.ssm.fallback);
+                providers.push(Box::new(discovery));
+            }
+            DiscoveryMode::Env => {}
+            DiscoveryMode::File => match &config.endpoints_file {
+                Some(path) => providers.push(Box::new(FileEndpointProvider::new(path))),
+                None if config.endpoints.is_empty() => anyhow::bail!(
+                    "--discovery file requires --endpoints-file <path> or an [endpoints] table"
+                ),
+                // The config's own [endpoints] table is the file
+                None => {}
+            },
+        }
+        providers.push(Box::new(ConfigEndpointProvider::new(
+            config.endpoints.clone(),
+        )));
+        Ok(providers)
+    }
+
+    async fn resolve_endpoints(
+        &self,
+        config: &RunConfig,
+        aws: Option<&SdkConfig>,
+    ) -> anyhow::Result<Endpoints> {
+        resolve_endpoints(&self.endpoint_providers(config, aws)?).await
+    }
+}
+
+/// Loaded only when SSM discovery or SigV4 signing needs it
+async fn aws_config(config: &RunConfig) -> Option<SdkConfig> {
+    if config.needs_aws() {
+        Some(load_aws_config(&config.region).await)
+    } else {
+        None
+    }
+}
+
+fn load_scenario(config: &RunConfig) -> anyhow::Result<Scenario> {
+    match &config.scenario {
+        Some(path) => Scenario::load(path),
+        None => Ok(Scenario::PetSite),
+    }
 }
 
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    let args = Args::parse();
+async fn main() -> anyhow::Result<ExitCode> {
+    let cli = Cli::parse();
 
-    println!("{}", "🚀 Microservice Load Tester".blue().bold());
+    match cli.command {
+        Some(Command::Config {
+            action: ConfigAction::Print(args),
+        }) => {
+            print!("{}", args.resolve_config()?.to_toml()?);
+            Ok(ExitCode::SUCCESS)
+        }
+        Some(Command::Run(args)) => run(args).await,
+        Some(Command::Discover(args)) => discover(args).await,
+        Some(Command::Validate(args)) => validate(args),
+        Some(Command::Report(args)) => {
+            let report = RunReport::load(&args.input)?;
+            display_results(
+                &report.scenarios,
+                &report.summary,
+                &report.config,
+                args.verbose,
+            );
+            if let Some(abort) = &report.aborted {
+                display_abort(abort);
+            }
+            display_timeseries(&report.timeseries, args.verbose);
+            if let Some(usage) = &report.food_catalog {
+                display_food_catalog(usage);
+            }
+            if let Some(usage) = &report.connections {
+                display_connections(usage);
+            }
+            if let Some(cleanup) = &report.cleanup {
+                display_cleanup(cleanup);
+            }
+            display_thresholds(&report.thresholds);
+            write_reports(&report, &args.outputs)?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Some(Command::Cleanup(args)) => cleanup(args).await,
+        Some(Command::Compare(args)) => {
+            let baseline = RunReport::load(&args.baseline)?;
+            let candidate = RunReport::load(&args.candidate)?;
+            let comparisons = compare_runs(&baseline, &candidate, &args.regression_config());
+            display_comparison(&baseline, &candidate, &comparisons);
+            if regressions(&comparisons).is_empty() {
+                Ok(ExitCode::SUCCESS)
+            } else {
+                Ok(ExitCode::from(EXIT_REGRESSION))
+            }
+        }
+        None => run(cli.args).await,
+    }
+}
+
+async fn cleanup(args: CleanupArgs) -> anyhow::Result<ExitCode> {
+    let mut config = args.args.resolve_config()?;
+    // The users file may be the one being read; never truncate it here
+    config.cleanup.users_file = None;
+    let scenario = load_scenario(&config)?;
+    let aws = aws_config(&config).await;
+    let endpoints = args.args.resolve_endpoints(&config, aws.as_ref()).await?;
+    scenario.validate(&endpoints)?;
+
+    let users = load_users(&args.users_from)?;
     println!(
         "{}",
         format!(
-            "Users: {}, Concurrent: {}, Region: {}",
-            args.users, args.concurrent, args.region
+            "🧹 Cleaning up {} users from {}",
+            users.len(),
+            args.users_from.display()
         )
-        .bright_black()
+        .blue()
+        .bold()
     );
 
-    let start_time = Instant::now();
+    let load_tester = LoadTester::new(
+        &config,
+        endpoints,
+        scenario,
+        args.args.dry_run,
+        args.args.verbose,
+        aws.as_ref(),
+    )?;
+    let cleanup = load_tester.run_cleanup(users).await;
+    display_cleanup(&cleanup);
+
+    if cleanup.failed_users().is_empty() && cleanup.uncleanable_users().is_empty() {
+        Ok(ExitCode::SUCCESS)
+    } else {
+        Ok(ExitCode::FAILURE)
+    }
+}
+
+async fn discover(args: Args) -> anyhow::Result<ExitCode> {
+    let config = args.resolve_config()?;
+    let scenario = load_scenario(&config)?;
 
-    // Discover endpoints from SSM
-    let discovery = SSMEndpointDiscovery::new(&args.region).await?;
-    let endpoints = discovery.discover_endpoints().await?;
+    println!("{}", "🔍 Endpoint Discovery".blue().bold());
+    let aws = aws_config(&config).await;
+    let endpoints = args.resolve_endpoints(&config, aws.as_ref()).await?;
 
-    if endpoints.is_empty() {
+    println!("{}", "\n🌐 Resolved Endpoints".blue().bold());
+    for (service, url) in endpoints.iter() {
+        println!("  {:<20} {}", service, url);
+    }
+
+    let missing = endpoints.missing(&scenario.services());
+    if !missing.is_empty() {
         println!(
             "{}",
-            "⚠️  No endpoints found in SSM. Using fallback endpoints.".yellow()
+            format!(
+                "⚠️  Scenario '{}' references unresolved services: {}",
+                scenario.name(),
+                missing.join(", ")
+            )
+            .yellow()
         );
     }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn validate(args: Args) -> anyhow::Result<ExitCode> {
+    let config = args.resolve_config()?;
+    let scenario = load_scenario(&config)?;
+    config.check_steps(&scenario)?;
+
+    let stages = config.effective_stages();
+    Feeders::load(&config, &scenario)?;
+    for (service, auth) in &config.auth {
+        auth.check_secrets(service)?;
+    }
+    let total_users: usize = stages.iter().map(|stage| stage.users).sum();
+    let run_id = config.run_id.clone().unwrap_or_else(users::new_run_id);
+    let user_ids =
+        config
+            .user_ids
+            .generate(total_users, config.seed.unwrap_or_default(), &run_id)?;
+    println!(
+        "{}",
+        format!(
+            "✓ Scenario '{}': {} steps across {}",
+            scenario.name(),
+            scenario.step_names().len(),
+            scenario.services().join(", ")
+        )
+        .green()
+    );
+    println!(
+        "{}",
+        format!(
+            "✓ {} stage(s), {} threshold(s), {} reporter(s), {} feeder file(s)",
+            stages.len(),
+            config.thresholds.count(),
+            config.reporters.len(),
+            config.feeders.len()
+        )
+        .green()
+    );
+    println!(
+        "{}",
+        format!(
+            "✓ {} user id(s) from the {} strategy, e.g. {}",
+            user_ids.len(),
+            format!("{:?}", config.user_ids.strategy).to_lowercase(),
+            user_ids.first().map(String::as_str).unwrap_or("-")
+        )
+        .green()
+    );
+    display_auth(&config, &scenario);
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Which services authenticate, and any configured for services the scenario never calls
+fn display_auth(config: &RunConfig, scenario: &Scenario) {
+    if config.auth.is_empty() {
+        return;
+    }
+    let services = scenario.services();
+    let (used, unused): (Vec<_>, Vec<_>) = config
+        .auth
+        .iter()
+        .partition(|(service, _)| services.contains(&service.as_str()));
+    if !used.is_empty() {
+        let used: Vec<String> = used
+            .iter()
+            .map(|(service, auth)| format!("{} ({})", service, auth.kind()))
+            .collect();
+        println!("{}", format!("🔐 Auth: {}", used.join(", ")).bright_black());
+    }
+    if !unused.is_empty() {
+        let unused: Vec<&str> = unused.iter().map(|(service, _)| service.as_str()).collect();
+        println!(
+            "{}",
+            format!(
+                "⚠️  Auth configured for services scenario '{}' never calls: {}",
+                scenario.name(),
+                unused.join(", ")
+            )
+            .yellow()
+        );
+    }
+}
+
+async fn run(args: Args) -> anyhow::Result<ExitCode> {
+    let mut config = args.resolve_config()?;
+    // Recorded in the report so the run can be repeated
+    let seed = *config.seed.get_or_insert_with(seed::random_seed);
+    let run_id = config.run_id.get_or_insert_with(users::new_run_id).clone();
+
+    println!("{}", "🚀 Microservice Load Tester".blue().bold());
+    println!(
+        "{}",
+        format!(
+            "Users: {}, Concurrent: {}, Region: {}, Run id: {}, Seed: {}",
+            config.users, config.concurrent, config.region, run_id, seed
+        )
+        .bright_black()
+    );
+
+    let scenario = load_scenario(&config)?;
+    config.check_steps(&scenario)?;
+    let aws = aws_config(&config).await;
+    let endpoints = args.resolve_endpoints(&config, aws.as_ref()).await?;
+    scenario.validate(&endpoints)?;
+    display_auth(&config, &scenario);
+
+    // Probe every endpoint before generating any load
+    if !args.dry_run {
+        let checks = run_preflight(&endpoints, &scenario.services()).await?;
+        display_preflight(&checks);
+
+        let failed: Vec<&str> = checks
+            .iter()
+            .filter(|check| !check.is_ready())
+            .map(|check| check.service.as_str())
+            .collect();
+        if !failed.is_empty() {
+            if args.skip_preflight {
+                println!(
+                    "{}",
+                    format!(
+                        "⚠️  Continuing despite failed preflight for: {}",
+                        failed.join(", ")
+                    )
+                    .yellow()
+                );
+            } else {
+                anyhow::bail!(
+                    "Preflight failed for: {} (use --skip-preflight to run anyway)",
+                    failed.join(", ")
+                );
+            }
+        }
+    }
+
+    // Load the baseline before generating load so a bad path fails fast
+    let baseline = config
+        .baseline
+        .as_deref()
+        .map(RunReport::load)
+        .transpose()?;
 
     // Initialize load tester
     let load_tester = LoadTester::new(
-        args.users,
-        args.concurrent,
-        endpoints,
+        &config,
+        endpoints.clone(),
+        scenario.clone(),
         args.dry_run,
         args.verbose,
-        args.rampup,
-    );
+        aws.as_ref(),
+    )?;
 
     // Run the load test
-    let results = load_tester.run_load_test().await?;
+    let started_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    let start_time = Instant::now();
+
+    // The first signal stops the run and keeps partial results; a second one exits at once
+    let stop = load_tester.stop_signal();
+    let mut signals = Signals::new()?;
+    let signals = tokio::spawn(async move {
+        let signal = signals.recv().await;
+        println!(
+            "{}",
+            format!(
+                "\n🛑 Received {} - no new scenarios will start, in-flight users skip to cleanup (send again to exit immediately)",
+                signal
+            )
+            .red()
+            .bold()
+        );
+        stop.interrupt(format!("received {}", signal));
+        let signal = signals.recv().await;
+        eprintln!(
+            "{}",
+            format!("Received {} again, exiting without results", signal).red()
+        );
+        std::process::exit(EXIT_INTERRUPTED.into());
+    });
+
+    let results = load_tester.run_load_test().await;
+    let total_time = start_time.elapsed();
+    // The verdict is the load's; the cleanup phase below can't change it
+    let aborted = load_tester.abort();
+
+    // Remove whatever users left behind, however their scenarios ended
+    let cleanup = if config.cleanup.enabled {
+        Some(load_tester.run_cleanup(load_tester.pending_cleanup()).await)
+    } else {
+        None
+    };
+    let uncleaned_users = load_tester.pending_cleanup();
+    signals.abort();
+    let results = results?;
+    let food_catalog = load_tester.food_catalog_usage();
+    let connections = load_tester.connection_usage();
+    let summary = LoadTestResults::from_scenarios(&results, total_time);
+    let timeseries = TimeSeries::from_scenarios(&results, total_time, config.window());
 
     // Display results
-    load_tester.display_results(&results, start_time.elapsed());
+    display_results(&results, &summary, &config, args.verbose);
+    if let Some(abort) = &aborted {
+        display_abort(abort);
+    }
+    display_timeseries(&timeseries, args.verbose);
+    if let Some(usage) = &food_catalog {
+        display_food_catalog(usage);
+    }
+    if let Some(usage) = &connections {
+        display_connections(usage);
+    }
+    if let Some(cleanup) = &cleanup {
+        display_cleanup(cleanup);
+    }
+
+    let threshold_results = config.thresholds.evaluate(&summary);
+    display_thresholds(&threshold_results);
+    let thresholds_passed = threshold_results.iter().all(|outcome| outcome.passed);
 
-    Ok(())
+    let mut report = RunReport {
+        scenario: scenario.name().to_string(),
+        started_at,
+        endpoints: endpoints
+            .iter()
+            .map(|(service, url)| (service.to_string(), url.to_string()))
+            .collect(),
+        config,
+        summary,
+        aborted,
+        cleanup,
+        uncleaned_users,
+        food_catalog,
+        connections,
+        timeseries,
+        thresholds: threshold_results,
+        regressions: Vec::new(),
+        scenarios: results,
+    };
+    if let Some(baseline) = &baseline {
+        let comparisons = compare_runs(baseline, &report, &report.config.regression);
+        display_comparison(baseline, &report, &comparisons);
+        report.regressions = regressions(&comparisons);
+    }
+    write_reports(&report, &report.config.reporters)?;
+
+    if report
+        .aborted
+        .as_ref()
+        .is_some_and(|abort| abort.interrupted)
+    {
+        println!("{}", "❌ Run interrupted; results are partial".red().bold());
+        Ok(ExitCode::from(EXIT_INTERRUPTED))
+    } else if report.aborted.as_ref().is_some_and(|abort| abort.exhausted) {
+        println!("{}", "❌ Run ended early: test data ran out".red().bold());
+        Ok(ExitCode::from(EXIT_DATA_EXHAUSTED))
+    } else if report.aborted.is_some() {
+        println!("{}", "❌ Run aborted by an abort condition".red().bold());
+        Ok(ExitCode::from(EXIT_ABORTED))
+    } else if !thresholds_passed {
+        println!("{}", "❌ One or more thresholds failed".red().bold());
+        Ok(ExitCode::from(EXIT_THRESHOLDS_FAILED))
+    } else if !report.regressions.is_empty() {
+        println!("{}", "❌ Regressed against the baseline run".red().bold());
+        Ok(ExitCode::from(EXIT_REGRESSION))
+    } else {
+        Ok(ExitCode::SUCCESS)
+    }
 }
@@ -0,0 +1,386 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Test card prefixes (BINs) with the card length and CVV length they use
+const TEST_CARDS: [(&str, usize, usize); 6] = [
+    ("411111", 16, 3), // Visa
+    ("401288", 16, 3), // Visa
+    ("555555", 16, 3), // Mastercard
+    ("510510", 16, 3), // Mastercard
+    ("601111", 16, 3), // Discover
+    ("378282", 15, 4), // American Express
+];
+
+const FIRST_NAMES: [&str; 16] = [
+    "Olivia", "Liam", "Emma", "Noah", "Ava", "Mateo", "Sofia", "Wei", "Amara", "Hiroshi", "Priya",
+    "Jamal", "Elena", "Kofi", "Ingrid", "Diego",
+];
+
+const LAST_NAMES: [&str; 16] = [
+    "Smith", "Garcia", "Chen", "Johnson", "Okafor", "Müller", "Patel", "Nguyen", "Kowalski",
+    "Rossi", "Tanaka", "Brown", "Haddad", "O'Brien", "Silva", "Andersen",
+];
+
+const STREETS: [&str; 12] = [
+    "Main St",
+    "Oak Ave",
+    "Pine St",
+    "Maple Dr",
+    "Cedar Ln",
+    "Elm St",
+    "Lakeview Rd",
+    "Sunset Blvd",
+    "2nd Ave",
+    "Park Pl",
+    "Hillcrest Way",
+    "River Rd",
+];
+
+/// City, state and a ZIP code in that city
+const CITIES: [(&str, &str, &str); 12] = [
+    ("Seattle", "WA", "98101"),
+    ("Portland", "OR", "97205"),
+    ("San Francisco", "CA", "94103"),
+    ("Austin", "TX", "78701"),
+    ("Denver", "CO", "80202"),
+    ("Chicago", "IL", "60601"),
+    ("Boston", "MA", "02108"),
+    ("New York", "NY", "10001"),
+    ("Atlanta", "GA", "30303"),
+    ("Miami", "FL", "33130"),
+    ("Phoenix", "AZ", "85004"),
+    ("Minneapolis", "MN", "55401"),
+];
+
+/// Synthetic checkout data:
+///
+/// ```toml
+/// [payment]
+/// invalid_ratio = 0.1
+/// invalid_kinds = ["expired", "bad_checksum"]
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PaymentConfig {
+    /// Share of checkouts sent with a deliberately invalid payment, 0 to 1
+    pub invalid_ratio: f64,
+    /// Ways a payment is made invalid, picked evenly
+    pub invalid_kinds: Vec<InvalidPayment>,
+}
+
+impl Default for PaymentConfig {
+    fn default() -> Self {
+        Self {
+            invalid_ratio: 0.0,
+            invalid_kinds: InvalidPayment::ALL.to_vec(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InvalidPayment {
+    /// Card number fails the Luhn check
+    BadChecksum,
+    /// Expiry date in the past
+    Expired,
+    /// CVV with too few digits
+    BadCvv,
+    /// Card number with too few digits
+    WrongLength,
+}
+
+impl InvalidPayment {
+    const ALL: [Self; 4] = [
+        Self::BadChecksum,
+        Self::Expired,
+        Self::BadCvv,
+        Self::WrongLength,
+    ];
+
+    /// Step the checkout is recorded under
+    pub fn step(&self) -> &'static str {
+        INVALID_CHECKOUT_STEPS[*self as usize]
+    }
+}
+
+/// Steps for checkouts sent with an invalid payment, in `InvalidPayment` order
+pub const INVALID_CHECKOUT_STEPS: [&str; 4] = [
+    "checkout_invalid_bad_checksum",
+    "checkout_invalid_expired",
+    "checkout_invalid_bad_cvv",
+    "checkout_invalid_wrong_length",
+];
+
+impl PaymentConfig {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if !(0.0..=1.0).contains(&self.invalid_ratio) {
+            anyhow::bail!("payment.invalid_ratio must be between 0 and 1");
+        }
+        if self.invalid_ratio > 0.0 && self.invalid_kinds.is_empty() {
+            anyhow::bail!("payment.invalid_kinds must list at least one kind");
+        }
+        Ok(())
+    }
+
+    /// A checkout body, and how its payment was made invalid, if it was
+    pub fn checkout(&self, rng: &mut impl Rng) -> (serde_json::Value, Option<InvalidPayment>) {
+        let invalid = if rng.gen_bool(self.invalid_ratio) {
+            self.invalid_kinds.choose(rng).copied()
+        } else {
+            None
+        };
+
+        let name = format!(
+            "{} {}",
+            FIRST_NAMES.choose(rng).unwrap(),
+            LAST_NAMES.choose(rng).unwrap()
+        );
+        let (prefix, length, cvv_length) = *TEST_CARDS.choose(rng).unwrap();
+        let mut card_number = card_number(prefix, length, rng);
+        let mut cvv: String = (0..cvv_length)
+            .map(|_| char::from(b'0' + rng.gen_range(0..10)))
+            .collect();
+
+        let (year, month) = current_year_month();
+        let months_ahead = match invalid {
+            Some(InvalidPayment::Expired) => -rng.gen_range(1..=24),
+            _ => rng.gen_range(1..=48),
+        };
+        let expiry = year * 12 + (month as i64 - 1) + months_ahead;
+
+        match invalid {
+            Some(InvalidPayment::BadChecksum) => {
+                let last = card_number.pop().and_then(|c| c.to_digit(10)).unwrap_or(0);
+                card_number.push(char::from_digit((last + 1) % 10, 10).unwrap());
+            }
+            Some(InvalidPayment::BadCvv) => cvv.truncate(cvv_length - 1),
+            Some(InvalidPayment::WrongLength) => card_number.truncate(12),
+            _ => {}
+        }
+
+        let shipping = address(&name, rng);
+        // Most people are billed where they live
+        let billing = if rng.gen_bool(0.8) {
+            shipping.clone()
+        } else {
+            address(&name, rng)
+        };
+
+        let payload = serde_json::json!({
+            "payment_method": {
+                "CreditCard": {
+                    "card_number": card_number,
+                    "expiry_month": expiry.rem_euclid(12) + 1,
+                    "expiry_year": expiry.div_euclid(12),
+                    "cvv": cvv,
+                    "cardholder_name": name
+                }
+            },
+            "shipping_address": shipping,
+            "billing_address": billing
+        });
+        (payload, invalid)
+    }
+}
+
+/// A Luhn-valid card number of `length` digits starting with `prefix`
+fn card_number(prefix: &str, length: usize, rng: &mut impl Rng) -> String {
+    let mut digits: Vec<u32> = prefix.chars().filter_map(|c| c.to_digit(10)).collect();
+    while digits.len() < length - 1 {
+        digits.push(rng.gen_range(0..10));
+    }
+    // Double every second digit counting from the right of the finished number
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(index, digit)| {
+            if index.is_multiple_of(2) {
+                let doubled = digit * 2;
+                if doubled > 9 {
+                    doubled - 9
+                } else {
+                    doubled
+                }
+            } else {
+                *digit
+            }
+        })
+        .sum();
+    digits.push((10 - sum % 10) % 10);
+    digits
+        .iter()
+        .map(|digit| char::from_digit(*digit, 10).unwrap())
+        .collect()
+}
+
+fn address(name: &str, rng: &mut impl Rng) -> serde_json::Value {
+    let (city, state, zip_code) = *CITIES.choose(rng).unwrap();
+    serde_json::json!({
+        "name": name,
+        "street": format!("{} {}", rng.gen_range(1..=9999), STREETS.choose(rng).unwrap()),
+        "city": city,
+        "state": state,
+        "zip_code": zip_code,
+        "country": "USA"
+    })
+}
+
+/// Today's year and month (1-12) in UTC
+pub fn current_year_month() -> (i64, u32) {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() / 86_400)
+        .unwrap_or_default() as i64;
+    let (year, month, _) = civil_from_days(days);
+    (year, month)
+}
+
+/// Year, month and day of the date `days` after 1970-01-01 (proleptic Gregorian)
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn luhn_valid(number: &str) -> bool {
+        let sum: u32 = number
+            .chars()
+            .rev()
+            .enumerate()
+            .map(|(index, c)| {
+                let digit = c.to_digit(10).unwrap();
+                match index % 2 {
+                    0 => digit,
+                    _ if digit * 2 > 9 => digit * 2 - 9,
+                    _ => digit * 2,
+                }
+            })
+            .sum();
+        sum.is_multiple_of(10)
+    }
+
+    fn card(payload: &serde_json::Value) -> &serde_json::Value {
+        &payload["payment_method"]["CreditCard"]
+    }
+
+    #[test]
+    fn card_numbers_pass_luhn_for_every_bin() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for (prefix, length, _) in TEST_CARDS {
+            for _ in 0..100 {
+                let number = card_number(prefix, length, &mut rng);
+                assert_eq!(number.len(), length, "{}", number);
+                assert!(number.starts_with(prefix), "{}", number);
+                assert!(luhn_valid(&number), "{} fails the Luhn check", number);
+            }
+        }
+    }
+
+    #[test]
+    fn luhn_check_matches_known_numbers() {
+        assert!(luhn_valid("4111111111111111"));
+        assert!(luhn_valid("378282246310005"));
+        assert!(!luhn_valid("4111111111111112"));
+    }
+
+    #[test]
+    fn valid_checkouts_pass_every_check() {
+        let config = PaymentConfig::default();
+        let mut rng = StdRng::seed_from_u64(2);
+        let (year, month) = current_year_month();
+        for _ in 0..200 {
+            let (payload, invalid) = config.checkout(&mut rng);
+            assert_eq!(invalid, None);
+            let card = card(&payload);
+            let number = card["card_number"].as_str().unwrap();
+            let expected_cvv = if number.len() == 15 { 4 } else { 3 };
+            assert!(luhn_valid(number));
+            assert!(matches!(number.len(), 15 | 16));
+            assert_eq!(card["cvv"].as_str().unwrap().len(), expected_cvv);
+            let expiry = (
+                card["expiry_year"].as_i64().unwrap(),
+                card["expiry_month"].as_u64().unwrap(),
+            );
+            assert!(expiry > (year, u64::from(month)));
+        }
+    }
+
+    #[test]
+    fn each_invalid_kind_fails_its_check() {
+        let (year, month) = current_year_month();
+        for kind in InvalidPayment::ALL {
+            let config = PaymentConfig {
+                invalid_ratio: 1.0,
+                invalid_kinds: vec![kind],
+            };
+            let mut rng = StdRng::seed_from_u64(3);
+            for _ in 0..100 {
+                let (payload, invalid) = config.checkout(&mut rng);
+                assert_eq!(invalid, Some(kind));
+                let card = card(&payload);
+                let number = card["card_number"].as_str().unwrap();
+                let cvv = card["cvv"].as_str().unwrap();
+                let full_length = if number.starts_with("37") { 15 } else { 16 };
+                let full_cvv = if number.starts_with("37") { 4 } else { 3 };
+                let expiry = (
+                    card["expiry_year"].as_i64().unwrap(),
+                    card["expiry_month"].as_u64().unwrap(),
+                );
+                match kind {
+                    InvalidPayment::BadChecksum => assert!(!luhn_valid(number), "{}", number),
+                    InvalidPayment::Expired => assert!(expiry < (year, u64::from(month))),
+                    InvalidPayment::BadCvv => assert!(cvv.len() < full_cvv, "{}", cvv),
+                    InvalidPayment::WrongLength => {
+                        assert!(number.len() < full_length, "{}", number)
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_steps_follow_kind_order() {
+        assert_eq!(
+            InvalidPayment::BadChecksum.step(),
+            "checkout_invalid_bad_checksum"
+        );
+        assert_eq!(
+            InvalidPayment::WrongLength.step(),
+            "checkout_invalid_wrong_length"
+        );
+    }
+
+    #[test]
+    fn civil_from_days_known_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(364), (1970, 12, 31));
+        assert_eq!(civil_from_days(365), (1971, 1, 1));
+        assert_eq!(civil_from_days(10_956), (1999, 12, 31));
+        assert_eq!(civil_from_days(10_957), (2000, 1, 1));
+        // 2000 is a leap year, 2100 is not
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(11_017), (2000, 3, 1));
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+        assert_eq!(civil_from_days(47_540), (2100, 2, 28));
+        assert_eq!(civil_from_days(47_541), (2100, 3, 1));
+    }
+}
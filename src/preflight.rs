@@ -0,0 +1,202 @@
+use colored::*;
+use futures::future::join_all;
+use reqwest::{Client, Url};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+use tokio_native_tls::{native_tls, TlsConnector};
+
+use crate::types::Endpoints;
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Outcome of probing one service endpoint before load starts
+#[derive(Debug)]
+pub struct PreflightResult {
+    pub service: String,
+    pub url: String,
+    pub dns: Option<Duration>,
+    pub tcp: Option<Duration>,
+    /// `None` for plain HTTP endpoints
+    pub tls: Option<Duration>,
+    pub http_status: Option<u16>,
+    pub http_time: Option<Duration>,
+    pub error: Option<String>,
+}
+
+impl PreflightResult {
+    pub fn is_ready(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Probe every listed service: DNS resolution, TCP connect, TLS handshake and one GET
+pub async fn run_preflight(
+    endpoints: &Endpoints,
+    services: &[&str],
+) -> anyhow::Result<Vec<PreflightResult>> {
+    let client = Client::builder().timeout(PROBE_TIMEOUT).build()?;
+
+    let probes = services.iter().map(|service| {
+        let url = endpoints.get(service).unwrap_or_default().to_string();
+        probe(&client, service.to_string(), url)
+    });
+
+    Ok(join_all(probes).await)
+}
+
+async fn probe(client: &Client, service: String, url: String) -> PreflightResult {
+    let mut result = PreflightResult {
+        service,
+        url,
+        dns: None,
+        tcp: None,
+        tls: None,
+        http_status: None,
+        http_time: None,
+        error: None,
+    };
+
+    if let Err(err) = probe_steps(client, &mut result).await {
+        result.error = Some(err.to_string());
+    }
+    result
+}
+
+async fn probe_steps(client: &Client, result: &mut PreflightResult) -> anyhow::Result<()> {
+    let url = Url::parse(&result.url)?;
+    let host = url
+        .host_str()
+        .ok_or_else(|| anyhow::anyhow!("URL has no host"))?
+        .to_string();
+    let port = url
+        .port_or_known_default()
+        .ok_or_else(|| anyhow::anyhow!("URL has no port"))?;
+
+    let start = Instant::now();
+    let addresses: Vec<SocketAddr> = timeout(
+        PROBE_TIMEOUT,
+        tokio::net::lookup_host((host.as_str(), port)),
+    )
+    .await
+    .map_err(|_| anyhow::anyhow!("DNS lookup timed out"))?
+    .map_err(|err| anyhow::anyhow!("DNS lookup failed: {}", err))?
+    .collect();
+    result.dns = Some(start.elapsed());
+    if addresses.is_empty() {
+        anyhow::bail!("DNS returned no addresses for {}", host);
+    }
+
+    let start = Instant::now();
+    let stream = connect_any(&addresses).await?;
+    result.tcp = Some(start.elapsed());
+
+    if url.scheme() == "https" {
+        let connector = TlsConnector::from(native_tls::TlsConnector::new()?);
+        let start = Instant::now();
+        timeout(PROBE_TIMEOUT, connector.connect(&host, stream))
+            .await
+            .map_err(|_| anyhow::anyhow!("TLS handshake timed out"))?
+            .map_err(|err| anyhow::anyhow!("TLS handshake failed: {}", err))?;
+        result.tls = Some(start.elapsed());
+    }
+
+    // Any HTTP response proves the endpoint is reachable; the status is only reported
+    let start = Instant::now();
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|err| anyhow::anyhow!("GET failed: {}", err))?;
+    result.http_time = Some(start.elapsed());
+    result.http_status = Some(response.status().as_u16());
+
+    Ok(())
+}
+
+/// Connect to the first resolved address that accepts, like the HTTP client does
+async fn connect_any(addresses: &[SocketAddr]) -> anyhow::Result<TcpStream> {
+    let mut failures = Vec::new();
+    for address in addresses {
+        match timeout(PROBE_TIMEOUT, TcpStream::connect(address)).await {
+            Ok(Ok(stream)) => return Ok(stream),
+            Ok(Err(err)) => failures.push(format!("{} failed: {}", address, err)),
+            Err(_) => failures.push(format!("{} timed out", address)),
+        }
+    }
+    anyhow::bail!("TCP connect to {}", failures.join("; "))
+}
+
+pub fn display_preflight(results: &[PreflightResult]) {
+    fn timing(duration: Option<Duration>) -> String {
+        duration
+            .map(|d| format!("{}ms", d.as_millis()))
+            .unwrap_or_else(|| "-".to_string())
+    }
+
+    println!("{}", "\n🩺 Preflight Checks".blue().bold());
+    println!("{}", "─".repeat(80).purple());
+    println!(
+        "{}",
+        format!(
+            "{:<20} {:>8} {:>8} {:>8} {:>14}  {}",
+            "SERVICE", "DNS", "TCP", "TLS", "HTTP", "RESULT"
+        )
+        .bright_black()
+    );
+
+    for result in results {
+        let http = match (result.http_status, result.http_time) {
+            (Some(status), Some(time)) => format!("{} ({}ms)", status, time.as_millis()),
+            _ => "-".to_string(),
+        };
+        let line = format!(
+            "{:<20} {:>8} {:>8} {:>8} {:>14}",
+            result.service,
+            timing(result.dns),
+            timing(result.tcp),
+            timing(result.tls),
+            http
+        );
+
+        match &result.error {
+            None => println!("{}  {}", line, "✓ ready".green()),
+            Some(error) => {
+                println!("{}  {}", line, "✗ not ready".red());
+                println!("{}", format!("    {}: {}", result.url, error).red());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    async fn closed_address() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap()
+    }
+
+    #[tokio::test]
+    async fn connects_to_a_later_address_when_the_first_refuses() {
+        let closed = closed_address().await;
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let open = listener.local_addr().unwrap();
+
+        let stream = connect_any(&[closed, open]).await.unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), open);
+    }
+
+    #[tokio::test]
+    async fn reports_every_address_when_none_accept() {
+        let first = closed_address().await;
+        let second = closed_address().await;
+
+        let err = connect_any(&[first, second]).await.unwrap_err().to_string();
+        assert!(err.contains(&first.to_string()), "{}", err);
+        assert!(err.contains(&second.to_string()), "{}", err);
+    }
+}
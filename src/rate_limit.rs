@@ -0,0 +1,216 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::endpoint_provider::parse_key_value;
+
+/// Request-rate caps that protect shared environments:
+///
+/// ```toml
+/// [rate_limit]
+/// requests_per_second = 200
+///
+/// [rate_limit.services]
+/// payforadoption = 20
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    /// Cap across all services; unset means unlimited
+    pub requests_per_second: Option<f64>,
+    /// Requests that may go out back to back before a cap applies
+    pub burst: u32,
+    /// Service name -> requests per second for that service
+    pub services: BTreeMap<String, f64>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_second: None,
+            burst: 1,
+            services: BTreeMap::new(),
+        }
+    }
+}
+
+impl RateLimitConfig {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.burst == 0 {
+            anyhow::bail!("rate_limit.burst must be at least 1");
+        }
+        let limits = self
+            .requests_per_second
+            .iter()
+            .map(|rate| ("rate_limit.requests_per_second".to_string(), *rate))
+            .chain(
+                self.services
+                    .iter()
+                    .map(|(service, rate)| (format!("rate_limit.services.{}", service), *rate)),
+            );
+        for (name, rate) in limits {
+            if rate <= 0.0 || !rate.is_finite() {
+                anyhow::bail!("{} must be greater than 0", name);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse a `--service-rate-limit` value such as `payforadoption=20`
+pub fn parse_service_rate(arg: &str) -> Result<(String, f64), String> {
+    let (service, rate) = parse_key_value(arg)?;
+    let rate = rate
+        .parse()
+        .map_err(|_| format!("invalid requests per second '{}'", rate))?;
+    Ok((service, rate))
+}
+
+/// A token bucket that hands out reservations: each caller takes a token
+/// (possibly driving the balance negative) and sleeps until its token is due,
+/// so waiting callers are served in order without polling
+struct TokenBucket {
+    rate: f64,
+    burst: f64,
+    state: Mutex<(f64, Instant)>,
+}
+
+impl TokenBucket {
+    fn new(rate: f64, burst: u32) -> Self {
+        Self {
+            rate,
+            burst: burst as f64,
+            state: Mutex::new((burst as f64, Instant::now())),
+        }
+    }
+
+    /// Take a token, waiting until it is available; returns the time waited
+    async fn acquire(&self) -> Duration {
+        let wait = self.reserve(Instant::now());
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+        wait
+    }
+
+    /// Take a token at `now`; returns how long until it is due
+    fn reserve(&self, now: Instant) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        let (tokens, last) = &mut *state;
+        *tokens = (*tokens + now.saturating_duration_since(*last).as_secs_f64() * self.rate)
+            .min(self.burst);
+        *last = (*last).max(now);
+        *tokens -= 1.0;
+        if *tokens < 0.0 {
+            Duration::from_secs_f64(-*tokens / self.rate)
+        } else {
+            Duration::ZERO
+        }
+    }
+}
+
+/// The global bucket plus one bucket per limited service
+pub struct RateLimiter {
+    global: Option<TokenBucket>,
+    services: HashMap<String, TokenBucket>,
+}
+
+impl RateLimiter {
+    pub fn new(config: &RateLimitConfig) -> Self {
+        Self {
+            global: config
+                .requests_per_second
+                .map(|rate| TokenBucket::new(rate, config.burst)),
+            services: config
+                .services
+                .iter()
+                .map(|(service, rate)| (service.clone(), TokenBucket::new(*rate, config.burst)))
+                .collect(),
+        }
+    }
+
+    /// Wait for the service's bucket, then the global one; returns the total time waited
+    pub async fn acquire(&self, service: &str) -> Duration {
+        let mut waited = Duration::ZERO;
+        if let Some(bucket) = self.services.get(service) {
+            waited += bucket.acquire().await;
+        }
+        if let Some(bucket) = &self.global {
+            waited += bucket.acquire().await;
+        }
+        waited
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(duration: Duration) -> u128 {
+        duration.as_millis()
+    }
+
+    #[test]
+    fn burst_goes_out_at_once_then_requests_queue_at_the_rate() {
+        let bucket = TokenBucket::new(10.0, 3);
+        let start = bucket.state.lock().unwrap().1;
+        let waits: Vec<u128> = (0..5).map(|_| millis(bucket.reserve(start))).collect();
+        assert_eq!(waits, [0, 0, 0, 100, 200]);
+    }
+
+    #[test]
+    fn tokens_refill_over_time_up_to_the_burst() {
+        let bucket = TokenBucket::new(10.0, 2);
+        let start = bucket.state.lock().unwrap().1;
+        bucket.reserve(start);
+        bucket.reserve(start);
+        assert_eq!(millis(bucket.reserve(start)), 100);
+
+        // An idle second refills only up to the burst
+        let later = start + Duration::from_secs(1);
+        let waits: Vec<u128> = (0..3).map(|_| millis(bucket.reserve(later))).collect();
+        assert_eq!(waits, [0, 0, 100]);
+    }
+
+    #[tokio::test]
+    async fn service_limits_apply_only_to_their_service() {
+        let config = RateLimitConfig {
+            services: BTreeMap::from([("payforadoption".to_string(), 1.0)]),
+            ..RateLimitConfig::default()
+        };
+        let limiter = RateLimiter::new(&config);
+        assert_eq!(limiter.acquire("payforadoption").await, Duration::ZERO);
+        for _ in 0..10 {
+            assert_eq!(limiter.acquire("petsearch").await, Duration::ZERO);
+        }
+        let bucket = &limiter.services["payforadoption"];
+        assert!(bucket.reserve(Instant::now()) > Duration::from_millis(900));
+    }
+
+    #[test]
+    fn rejects_non_positive_rates_and_zero_burst() {
+        let mut config = RateLimitConfig {
+            requests_per_second: Some(0.0),
+            ..RateLimitConfig::default()
+        };
+        assert!(config.validate().is_err());
+        config.requests_per_second = Some(5.0);
+        config.services.insert("petfood".to_string(), f64::INFINITY);
+        assert!(config.validate().is_err());
+        config.services.clear();
+        config.burst = 0;
+        assert!(config.validate().is_err());
+        config.burst = 1;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn parses_service_rates() {
+        assert_eq!(
+            parse_service_rate("payforadoption=2.5").unwrap(),
+            ("payforadoption".to_string(), 2.5)
+        );
+        assert!(parse_service_rate("payforadoption=fast").is_err());
+    }
+}
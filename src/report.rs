@@ -0,0 +1,540 @@
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::abort::Abort;
+use crate::cleanup::CleanupResult;
+use crate::compare::Regression;
+use crate::config::RunConfig;
+use crate::errors::group_errors;
+use crate::food_catalog::FoodCatalogUsage;
+use crate::html_report;
+use crate::junit_report;
+use crate::session::ConnectionUsage;
+use crate::thresholds::ThresholdResult;
+use crate::timeseries::TimeSeries;
+use crate::types::{LoadTestResults, RequestResult, UserScenarioResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// The full run (config, summary, every request) as one JSON document
+    Json,
+    /// One `run` header line followed by one line per scenario
+    Ndjson,
+    /// A self-contained page with charts and tables, for sharing
+    Html,
+    /// Thresholds, step assertions and regressions as test cases for CI
+    Junit,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "json" => Ok(Self::Json),
+            "ndjson" => Ok(Self::Ndjson),
+            "html" => Ok(Self::Html),
+            "junit" => Ok(Self::Junit),
+            other => Err(format!(
+                "unknown output format '{}' (expected json, ndjson, html or junit)",
+                other
+            )),
+        }
+    }
+}
+
+impl OutputFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Ndjson => "ndjson",
+            Self::Html => "html",
+            Self::Junit => "junit",
+        }
+    }
+}
+
+/// Error kinds listed in the non-verbose summary
+const TOP_ERRORS: usize = 5;
+
+/// A report destination written as `<format>=<path>`, e.g. `json=results.json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct OutputTarget {
+    pub format: OutputFormat,
+    pub path: PathBuf,
+}
+
+impl FromStr for OutputTarget {
+    type Err = String;
+
+    fn from_str(arg: &str) -> Result<Self, Self::Err> {
+        let (format, path) = arg
+            .split_once('=')
+            .filter(|(_, path)| !path.is_empty())
+            .ok_or_else(|| format!("expected <format>=<path>, got '{}'", arg))?;
+        Ok(Self {
+            format: format.parse()?,
+            path: PathBuf::from(path),
+        })
+    }
+}
+
+impl TryFrom<String> for OutputTarget {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<OutputTarget> for String {
+    fn from(target: OutputTarget) -> Self {
+        format!("{}={}", target.format.name(), target.path.display())
+    }
+}
+
+/// Everything known about a finished run, as saved by the reporters
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RunReport {
+    pub scenario: String,
+    /// Unix timestamp (seconds) when the run started
+    pub started_at: u64,
+    pub config: RunConfig,
+    pub endpoints: BTreeMap<String, String>,
+    pub summary: LoadTestResults,
+    /// Set when an abort condition stopped the run early
+    #[serde(default)]
+    pub aborted: Option<Abort>,
+    /// The end-of-run cleanup phase, unless it was disabled
+    #[serde(default)]
+    pub cleanup: Option<CleanupResult>,
+    /// Users whose changes the run, including its cleanup phase, didn't undo;
+    /// `cleanup --users-from` retries these
+    #[serde(default)]
+    pub uncleaned_users: Vec<String>,
+    /// Where the built-in scenario's food ids came from
+    #[serde(default)]
+    pub food_catalog: Option<FoodCatalogUsage>,
+    /// Connections opened by the run, unless it was a dry run
+    #[serde(default)]
+    pub connections: Option<ConnectionUsage>,
+    #[serde(default)]
+    pub timeseries: TimeSeries,
+    pub thresholds: Vec<ThresholdResult>,
+    /// Regressions against `config.baseline`, if one was given
+    #[serde(default)]
+    pub regressions: Vec<Regression>,
+    pub scenarios: Vec<UserScenarioResult>,
+}
+
+impl RunReport {
+    /// Load a report written by the `json` or `ndjson` reporter
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|err| anyhow::anyhow!("Failed to read {}: {}", path.display(), err))?;
+        let is_ndjson = path
+            .extension()
+            .is_some_and(|extension| extension == "ndjson" || extension == "jsonl");
+
+        let report = if is_ndjson {
+            Self::from_ndjson(&contents)
+        } else {
+            serde_json::from_str(&contents).map_err(anyhow::Error::from)
+        };
+        report.map_err(|err| anyhow::anyhow!("Invalid report {}: {}", path.display(), err))
+    }
+
+    fn to_ndjson(&self) -> anyhow::Result<String> {
+        let mut header = serde_json::to_value(self)?;
+        if let Some(header) = header.as_object_mut() {
+            header.remove("scenarios");
+            header.insert("type".to_string(), "run".into());
+        }
+
+        let mut lines = vec![serde_json::to_string(&header)?];
+        for scenario in &self.scenarios {
+            let mut line = serde_json::to_value(scenario)?;
+            if let Some(line) = line.as_object_mut() {
+                line.insert("type".to_string(), "scenario".into());
+            }
+            lines.push(serde_json::to_string(&line)?);
+        }
+        Ok(lines.join("\n") + "\n")
+    }
+
+    fn from_ndjson(contents: &str) -> anyhow::Result<Self> {
+        let mut header = None;
+        let mut scenarios = Vec::new();
+
+        for (number, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let mut record: serde_json::Value = serde_json::from_str(line)
+                .map_err(|err| anyhow::anyhow!("line {}: {}", number + 1, err))?;
+            let record_type = record
+                .as_object_mut()
+                .and_then(|record| record.remove("type"))
+                .and_then(|record_type| record_type.as_str().map(str::to_string));
+
+            match record_type.as_deref() {
+                Some("run") => {
+                    if let Some(record) = record.as_object_mut() {
+                        record.insert("scenarios".to_string(), serde_json::json!([]));
+                    }
+                    header = Some(serde_json::from_value::<RunReport>(record)?);
+                }
+                Some("scenario") => scenarios.push(serde_json::from_value(record)?),
+                other => anyhow::bail!("line {}: unknown record type {:?}", number + 1, other),
+            }
+        }
+
+        let mut report = header.ok_or_else(|| anyhow::anyhow!("missing run header line"))?;
+        report.scenarios = scenarios;
+        Ok(report)
+    }
+
+    pub fn write(&self, target: &OutputTarget) -> anyhow::Result<()> {
+        let contents = match target.format {
+            OutputFormat::Json => serde_json::to_string_pretty(self)?,
+            OutputFormat::Ndjson => self.to_ndjson()?,
+            OutputFormat::Html => html_report::render(self),
+            OutputFormat::Junit => junit_report::render(self),
+        };
+        std::fs::write(&target.path, contents)
+            .map_err(|err| anyhow::anyhow!("Failed to write {}: {}", target.path.display(), err))
+    }
+}
+
+/// Write the report to every configured target
+pub fn write_reports(report: &RunReport, targets: &[OutputTarget]) -> anyhow::Result<()> {
+    for target in targets {
+        report.write(target)?;
+        println!(
+            "{}",
+            format!(
+                "📝 Wrote {} report to {}",
+                target.format.name(),
+                target.path.display()
+            )
+            .bright_black()
+        );
+    }
+    Ok(())
+}
+
+pub fn display_results(
+    results: &[UserScenarioResult],
+    summary: &LoadTestResults,
+    config: &RunConfig,
+    verbose: bool,
+) {
+    let stages = config.effective_stages();
+    let all_requests: Vec<&RequestResult> = results.iter().flat_map(|r| &r.requests).collect();
+
+    println!("{}", "\n📊 Load Test Results".green().bold());
+    println!("{}", "═".repeat(50).purple());
+
+    println!("{}", format!("Total Scenarios: {}", results.len()).blue());
+    if let Some(run_id) = &config.run_id {
+        println!("{}", format!("Run id: {}", run_id).bright_black());
+    }
+    if let Some(seed) = config.seed {
+        println!(
+            "{}",
+            format!("Seed: {} (repeat with --seed {})", seed, seed).bright_black()
+        );
+    }
+    println!(
+        "{}",
+        format!("Total Requests: {}", summary.total_requests).blue()
+    );
+    println!(
+        "{}",
+        format!("✓ Successful: {}", summary.successful_requests).green()
+    );
+    println!("{}", format!("✗ Failed: {}", summary.failed_requests).red());
+    println!(
+        "{}",
+        format!("Success Rate: {:.1}%", summary.success_rate).yellow()
+    );
+    if summary.retries > 0 {
+        // Retries are kept apart from failures so a flaky service is still visible
+        println!(
+            "{}",
+            format!(
+                "🔁 Retries: {} ({} requests recovered after retrying)",
+                summary.retries, summary.recovered_requests
+            )
+            .yellow()
+        );
+    }
+    println!(
+        "{}",
+        format!(
+            "Average Response Time: {}ms",
+            summary.average_response_time.as_millis()
+        )
+        .cyan()
+    );
+    println!(
+        "{}",
+        format!(
+            "Response Time p50/p95/p99: {}ms / {}ms / {}ms",
+            summary.p50_response_time.as_millis(),
+            summary.p95_response_time.as_millis(),
+            summary.p99_response_time.as_millis()
+        )
+        .cyan()
+    );
+    if !summary.total_limiter_wait.is_zero() {
+        println!(
+            "{}",
+            format!(
+                "⏳ Rate Limiter Wait: {}ms avg, {:.1}s total (excluded from response times)",
+                summary.average_limiter_wait.as_millis(),
+                summary.total_limiter_wait.as_secs_f64()
+            )
+            .cyan()
+        );
+    }
+    println!(
+        "{}",
+        format!("Requests/Second: {:.1}", summary.requests_per_second).magenta()
+    );
+    println!(
+        "{}",
+        format!("Total Test Time: {}ms", summary.total_test_time.as_millis()).purple()
+    );
+
+    if stages.len() > 1 {
+        let stages: Vec<String> = stages
+            .iter()
+            .map(|stage| format!("{} ({}×{})", stage.name, stage.users, stage.concurrent))
+            .collect();
+        println!("{}", format!("Stages: {}", stages.join(" → ")).purple());
+    } else if let Some(stage) = stages.first().filter(|stage| stage.rampup > 0) {
+        println!("{}", format!("Ramp-up Period: {}s", stage.rampup).purple());
+    }
+
+    // Show detailed information only in verbose mode
+    if verbose {
+        // Show detailed failed request information
+        let failed_request_details: Vec<&RequestResult> = all_requests
+            .iter()
+            .filter(|r| !r.success)
+            .copied()
+            .collect();
+        if !failed_request_details.is_empty() {
+            println!("{}", "\n❌ Failed Requests Details:".red().bold());
+            println!("{}", "─".repeat(80).purple());
+
+            for (i, request) in failed_request_details.iter().enumerate() {
+                println!(
+                    "{}",
+                    format!(
+                        "{}. {} {} ({})",
+                        i + 1,
+                        request.method,
+                        request.url,
+                        request.user_id
+                    )
+                    .red()
+                );
+
+                if request.status > 0 {
+                    println!("{}", format!("   Status: {}", request.status).yellow());
+                }
+
+                if let Some(kind) = request.failure_kind() {
+                    println!("{}", format!("   Category: {}", kind).yellow());
+                }
+
+                if let Some(error) = &request.error {
+                    println!("{}", format!("   Error: {}", error).red());
+                }
+
+                println!(
+                    "{}",
+                    format!("   Response Time: {}ms", request.response_time.as_millis()).cyan()
+                );
+                println!();
+            }
+        }
+
+        // Show failed scenarios summary
+        let failed_scenarios: Vec<&UserScenarioResult> =
+            results.iter().filter(|r| !r.success).collect();
+        if !failed_scenarios.is_empty() {
+            println!("{}", "📋 Failed Scenarios Summary:".red().bold());
+            for scenario in failed_scenarios {
+                let failed_count = scenario.requests.iter().filter(|r| !r.success).count();
+                let total_count = scenario.requests.len();
+                println!(
+                    "{}",
+                    format!(
+                        "  {}: {}/{} requests failed",
+                        scenario.user_id, failed_count, total_count
+                    )
+                    .red()
+                );
+            }
+        }
+
+        // Show request breakdown by endpoint
+        println!("{}", "\n📈 Request Breakdown by Endpoint:".blue().bold());
+        println!("{}", "─".repeat(80).purple());
+
+        let mut endpoint_stats = std::collections::HashMap::new();
+        for request in &all_requests {
+            let endpoint = request.url.split('?').next().unwrap_or(&request.url);
+            let stats = endpoint_stats.entry(endpoint.to_string()).or_insert((0, 0));
+            if request.success {
+                stats.0 += 1;
+            } else {
+                stats.1 += 1;
+            }
+        }
+
+        for (endpoint, (success, failed)) in endpoint_stats {
+            let total = success + failed;
+            let success_rate = if total > 0 {
+                (success as f64 / total as f64) * 100.0
+            } else {
+                0.0
+            };
+            let status_color = if success_rate >= 90.0 {
+                "green"
+            } else if success_rate >= 70.0 {
+                "yellow"
+            } else {
+                "red"
+            };
+
+            match status_color {
+                "green" => println!(
+                    "{}",
+                    format!(
+                        "  ✓ {}: {}/{} ({:.1}%)",
+                        endpoint, success, total, success_rate
+                    )
+                    .green()
+                ),
+                "yellow" => println!(
+                    "{}",
+                    format!(
+                        "  ⚠ {}: {}/{} ({:.1}%)",
+                        endpoint, success, total, success_rate
+                    )
+                    .yellow()
+                ),
+                "red" => println!(
+                    "{}",
+                    format!(
+                        "  ✗ {}: {}/{} ({:.1}%)",
+                        endpoint, success, total, success_rate
+                    )
+                    .red()
+                ),
+                _ => println!(
+                    "  • {}: {}/{} ({:.1}%)",
+                    endpoint, success, total, success_rate
+                ),
+            }
+        }
+
+        // Wide enough for the longest step name
+        let width = summary
+            .steps
+            .iter()
+            .map(|step| step.step.chars().count())
+            .max()
+            .unwrap_or_default()
+            .max(24);
+        println!("{}", "\n📋 Step Breakdown:".blue().bold());
+        println!("{}", "─".repeat(80).purple());
+        println!(
+            "{}",
+            format!(
+                "  {:<width$} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8}",
+                "STEP", "REQS", "ERR%", "RETRIES", "WAIT", "P50", "P95", "P99"
+            )
+            .bright_black()
+        );
+        for step in &summary.steps {
+            println!(
+                "  {:<width$} {:>8} {:>7.1}% {:>8} {:>6}ms {:>6}ms {:>6}ms {:>6}ms",
+                step.step,
+                step.total_requests,
+                step.error_rate,
+                step.retries,
+                step.average_limiter_wait.as_millis(),
+                step.p50_response_time.as_millis(),
+                step.p95_response_time.as_millis(),
+                step.p99_response_time.as_millis()
+            );
+        }
+
+        let failing_steps: Vec<_> = summary
+            .steps
+            .iter()
+            .filter(|step| !step.errors.is_empty() || !step.retried_errors.is_empty())
+            .collect();
+        if !failing_steps.is_empty() {
+            println!("{}", "\n🧮 Errors by Step:".blue().bold());
+            println!("{}", "─".repeat(80).purple());
+            for step in failing_steps {
+                let errors: Vec<String> = step
+                    .errors
+                    .iter()
+                    .map(|(kind, count)| format!("{} × {}", count, kind))
+                    .chain(
+                        step.retried_errors
+                            .iter()
+                            .map(|(kind, count)| format!("{} × {} (retried)", count, kind)),
+                    )
+                    .collect();
+                println!(
+                    "{}",
+                    format!("  {:<width$} {}", step.step, errors.join(", ")).red()
+                );
+            }
+        }
+    } else if summary.failed_requests > 0 {
+        // In non-verbose mode, just show the most frequent failures
+        println!("{}", "\n🔥 Top Errors:".red().bold());
+        for group in group_errors(all_requests.iter().copied())
+            .iter()
+            .take(TOP_ERRORS)
+        {
+            println!(
+                "{}",
+                format!(
+                    "  {:>5} × {:<18} in {}",
+                    group.count,
+                    group.kind.to_string(),
+                    group.steps.join(", ")
+                )
+                .red()
+            );
+            println!(
+                "{}",
+                format!("          e.g. {}", group.sample_url).bright_black()
+            );
+        }
+        println!(
+            "{}",
+            format!(
+                "\n⚠️  {} requests failed. Use --verbose for detailed error information.",
+                summary.failed_requests
+            )
+            .yellow()
+        );
+    }
+
+    println!("{}", format!("\n{}", "═".repeat(50)).purple());
+}
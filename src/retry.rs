@@ -0,0 +1,288 @@
+use rand::Rng;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::errors::ErrorKind;
+use crate::types::RequestResult;
+
+/// Methods that are safe to send twice
+const IDEMPOTENT_METHODS: [&str; 5] = ["GET", "HEAD", "OPTIONS", "PUT", "DELETE"];
+
+/// When and how a failed request is sent again
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryPolicy {
+    /// Total attempts per request, including the first (1 = no retries)
+    pub max_attempts: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    /// Backoff growth per attempt
+    pub multiplier: f64,
+    /// Fraction of each backoff that is randomized (0 = fixed, 1 = full jitter)
+    pub jitter: f64,
+    /// Response statuses worth retrying
+    pub retry_on_status: Vec<u16>,
+    /// Transport error kinds worth retrying
+    pub retry_on: Vec<ErrorKind>,
+    /// Also retry POST requests, which may not be safe to repeat
+    pub retry_non_idempotent: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            initial_backoff_ms: 100,
+            max_backoff_ms: 2000,
+            multiplier: 2.0,
+            jitter: 0.5,
+            retry_on_status: vec![502, 503, 504],
+            retry_on: vec![
+                ErrorKind::ConnectRefused,
+                ErrorKind::ConnectTimeout,
+                ErrorKind::Connect,
+                ErrorKind::RequestTimeout,
+            ],
+            retry_non_idempotent: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Whether a failed attempt should be sent again
+    pub fn should_retry(&self, result: &RequestResult, attempt: u32) -> bool {
+        if attempt >= self.max_attempts {
+            return false;
+        }
+        if !self.retry_non_idempotent && !IDEMPOTENT_METHODS.contains(&result.method.as_str()) {
+            return false;
+        }
+        match result.failure_kind() {
+            Some(ErrorKind::Http(status)) => self.retry_on_status.contains(&status),
+            Some(kind) => self.retry_on.contains(&kind),
+            None => false,
+        }
+    }
+
+    /// Delay before the attempt after `attempt`, growing exponentially up to the cap
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponential =
+            self.initial_backoff_ms as f64 * self.multiplier.powi(attempt.saturating_sub(1) as i32);
+        let capped = exponential.min(self.max_backoff_ms as f64);
+        let jitter = capped * self.jitter * rand::thread_rng().gen::<f64>();
+        Duration::from_secs_f64((capped - jitter).max(0.0) / 1000.0)
+    }
+
+    pub fn validate(&self, scope: &str) -> anyhow::Result<()> {
+        if self.max_attempts == 0 {
+            anyhow::bail!("{}.max_attempts must be at least 1", scope);
+        }
+        if !(0.0..=1.0).contains(&self.jitter) {
+            anyhow::bail!("{}.jitter must be between 0 and 1", scope);
+        }
+        if self.multiplier < 1.0 {
+            anyhow::bail!("{}.multiplier must be at least 1", scope);
+        }
+        Ok(())
+    }
+}
+
+/// Fields of a retry policy, each optional, as set in `[retry.steps.<name>]`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryOverrides {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_attempts: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub initial_backoff_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_backoff_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub multiplier: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jitter: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_on_status: Option<Vec<u16>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_on: Option<Vec<ErrorKind>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_non_idempotent: Option<bool>,
+}
+
+impl RetryOverrides {
+    /// `base` with every field set here replaced
+    pub fn apply(&self, base: &RetryPolicy) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: self.max_attempts.unwrap_or(base.max_attempts),
+            initial_backoff_ms: self.initial_backoff_ms.unwrap_or(base.initial_backoff_ms),
+            max_backoff_ms: self.max_backoff_ms.unwrap_or(base.max_backoff_ms),
+            multiplier: self.multiplier.unwrap_or(base.multiplier),
+            jitter: self.jitter.unwrap_or(base.jitter),
+            retry_on_status: self
+                .retry_on_status
+                .clone()
+                .unwrap_or_else(|| base.retry_on_status.clone()),
+            retry_on: self
+                .retry_on
+                .clone()
+                .unwrap_or_else(|| base.retry_on.clone()),
+            retry_non_idempotent: self
+                .retry_non_idempotent
+                .unwrap_or(base.retry_non_idempotent),
+        }
+    }
+}
+
+/// Retry policies, with optional per-step overrides that inherit every field
+/// they don't set from the default:
+///
+/// ```toml
+/// [retry]
+/// max_attempts = 3
+///
+/// [retry.steps.checkout]
+/// max_attempts = 1
+/// ```
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RetryConfig {
+    #[serde(flatten)]
+    pub default: RetryPolicy,
+    /// Step name -> fields that differ from the default
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub steps: BTreeMap<String, RetryOverrides>,
+}
+
+// Written by hand because serde can't deny unknown fields next to `flatten`:
+// the default policy's fields are read apart from `steps`, so typos still fail
+impl<'de> Deserialize<'de> for RetryConfig {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut table = serde_json::Map::<String, serde_json::Value>::deserialize(deserializer)?;
+        let steps = match table.remove("steps") {
+            Some(steps) => serde_json::from_value(steps)
+                .map_err(|err| D::Error::custom(format!("steps: {}", err)))?,
+            None => BTreeMap::new(),
+        };
+        let default = serde_json::from_value(table.into()).map_err(D::Error::custom)?;
+        Ok(Self { default, steps })
+    }
+}
+
+impl RetryConfig {
+    pub fn policy(&self, step: &str) -> Cow<'_, RetryPolicy> {
+        match self.steps.get(step) {
+            Some(overrides) => Cow::Owned(overrides.apply(&self.default)),
+            None => Cow::Borrowed(&self.default),
+        }
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        self.default.validate("retry")?;
+        for (step, overrides) in &self.steps {
+            overrides
+                .apply(&self.default)
+                .validate(&format!("retry.steps.{}", step))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failed(method: &str, status: u16, error_kind: Option<ErrorKind>) -> RequestResult {
+        RequestResult {
+            method: method.to_string(),
+            status,
+            error_kind,
+            ..RequestResult::default()
+        }
+    }
+
+    fn policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            jitter: 0.0,
+            ..RetryPolicy::default()
+        }
+    }
+
+    #[test]
+    fn backoff_grows_by_the_multiplier_up_to_the_cap() {
+        let policy = policy(5);
+        let delays: Vec<u128> = (1..=6).map(|n| policy.backoff(n).as_millis()).collect();
+        assert_eq!(delays, [100, 200, 400, 800, 1600, 2000]);
+    }
+
+    #[test]
+    fn jitter_only_shortens_the_backoff() {
+        let policy = RetryPolicy {
+            jitter: 1.0,
+            ..policy(3)
+        };
+        for _ in 0..100 {
+            assert!(policy.backoff(2) <= Duration::from_millis(200));
+        }
+    }
+
+    #[test]
+    fn retries_listed_failures_until_attempts_run_out() {
+        let policy = policy(3);
+        let unavailable = failed("GET", 503, None);
+        assert!(policy.should_retry(&unavailable, 1));
+        assert!(policy.should_retry(&unavailable, 2));
+        assert!(!policy.should_retry(&unavailable, 3));
+
+        assert!(policy.should_retry(&failed("GET", 0, Some(ErrorKind::ConnectRefused)), 1));
+        assert!(!policy.should_retry(&failed("GET", 404, None), 1));
+        assert!(!policy.should_retry(
+            &RequestResult {
+                success: true,
+                ..failed("GET", 200, None)
+            },
+            1
+        ));
+    }
+
+    #[test]
+    fn post_is_retried_only_when_allowed() {
+        let unavailable = failed("POST", 503, None);
+        assert!(!policy(3).should_retry(&unavailable, 1));
+        let policy = RetryPolicy {
+            retry_non_idempotent: true,
+            ..policy(3)
+        };
+        assert!(policy.should_retry(&unavailable, 1));
+    }
+
+    #[test]
+    fn step_overrides_inherit_unset_fields() {
+        let config: RetryConfig = toml::from_str(
+            r#"
+            max_attempts = 3
+            retry_on_status = [500]
+
+            [steps.checkout]
+            max_attempts = 1
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.policy("search").max_attempts, 3);
+        let checkout = config.policy("checkout");
+        assert_eq!(checkout.max_attempts, 1);
+        assert_eq!(checkout.retry_on_status, [500]);
+    }
+
+    #[test]
+    fn rejects_unknown_fields_and_invalid_overrides() {
+        assert!(toml::from_str::<RetryConfig>("max_atempts = 3").is_err());
+        assert!(toml::from_str::<RetryConfig>("[steps.checkout]\nmax_atempts = 3").is_err());
+
+        let config: RetryConfig = toml::from_str("[steps.checkout]\njitter = 2.0").unwrap();
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.starts_with("retry.steps.checkout.jitter"), "{}", err);
+    }
+}
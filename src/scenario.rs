@@ -0,0 +1,274 @@
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+use crate::feeders::PETSITE_FEEDERS;
+use crate::payment::INVALID_CHECKOUT_STEPS;
+use crate::template::{self, Expr};
+use crate::types::{services, Endpoints};
+
+/// Services the built-in PetSite scenario sends requests to
+pub const PETSITE_SERVICES: [&str; 4] = [
+    services::PETSEARCH,
+    services::PAYFORADOPTION,
+    services::PETLISTADOPTIONS,
+    services::PETFOOD,
+];
+
+/// Steps recorded by the built-in PetSite scenario, in request order, with the service each calls
+pub const PETSITE_STEPS: [(&str, &str); 24] = [
+    ("list_pets", services::PETSEARCH),
+    ("search_by_color", services::PETSEARCH),
+    ("search_by_type", services::PETSEARCH),
+    ("search_puppy", services::PETSEARCH),
+    ("adopt_puppy", services::PAYFORADOPTION),
+    ("search_kitten", services::PETSEARCH),
+    ("adopt_kitten", services::PAYFORADOPTION),
+    ("search_bunny", services::PETSEARCH),
+    ("adopt_bunny", services::PAYFORADOPTION),
+    ("list_adoptions", services::PETLISTADOPTIONS),
+    ("list_foods", services::PETFOOD),
+    ("filter_foods", services::PETFOOD),
+    ("search_foods", services::PETFOOD),
+    ("food_detail", services::PETFOOD),
+    ("view_cart", services::PETFOOD),
+    ("add_to_cart", services::PETFOOD),
+    ("update_cart", services::PETFOOD),
+    ("checkout", services::PETFOOD),
+    // Replace `checkout` when the payment is made invalid on purpose
+    (INVALID_CHECKOUT_STEPS[0], services::PETFOOD),
+    (INVALID_CHECKOUT_STEPS[1], services::PETFOOD),
+    (INVALID_CHECKOUT_STEPS[2], services::PETFOOD),
+    (INVALID_CHECKOUT_STEPS[3], services::PETFOOD),
+    ("empty_cart", services::PETFOOD),
+    ("cleanup_adoptions", services::PAYFORADOPTION),
+];
+
+const SUPPORTED_METHODS: [&str; 4] = ["GET", "POST", "PUT", "DELETE"];
+
+/// A scenario loaded from a TOML file. Each step names the service it calls,
+/// so new services only need an endpoint, not code changes:
+///
+/// ```toml
+/// name = "status-updater smoke test"
+///
+/// [[steps]]
+/// name = "list adoptions"
+/// service = "petlistadoptions"
+///
+/// [[steps]]
+/// name = "update status"
+/// service = "statusupdater"
+/// method = "PUT"
+/// body = { pettype = "puppy", petid = "001", petavailability = "yes" }
+/// expect_status = 200
+///
+/// [[steps]]
+/// name = "get status"
+/// service = "statusupdater"
+/// path = "/{{user_id}}"
+/// headers = { "X-Request-Id" = "{{uuid}}" }
+/// capture = { status = "/petavailability" }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScenarioDefinition {
+    pub name: String,
+    pub steps: Vec<StepDefinition>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StepDefinition {
+    pub name: String,
+    pub service: String,
+    #[serde(default = "default_method")]
+    pub method: String,
+    /// Appended to the service endpoint; a template, see `template::Expr`
+    #[serde(default)]
+    pub path: String,
+    /// Sent on top of `http.headers`; values are templated like `path`
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// JSON body sent with POST and PUT requests; its strings are templated like `path`
+    pub body: Option<serde_json::Value>,
+    /// Variables read from the JSON response by JSON pointer, e.g. `{ pet_id = "/0/petid" }`,
+    /// for later steps to use as `{{pet_id}}`
+    #[serde(default)]
+    pub capture: BTreeMap<String, String>,
+    /// Required response status; by default any 2xx or 3xx passes
+    pub expect_status: Option<u16>,
+    /// Undoes earlier steps, so it still runs when the run is interrupted
+    #[serde(default)]
+    pub cleanup: bool,
+}
+
+impl StepDefinition {
+    /// Every expression in the path, headers and body
+    fn expressions(&self) -> Result<Vec<Expr<'_>>, String> {
+        let mut found = template::expressions(&self.path)?;
+        for value in self.headers.values() {
+            found.extend(template::expressions(value)?);
+        }
+        if let Some(body) = &self.body {
+            template::json_expressions(body, &mut found)?;
+        }
+        Ok(found)
+    }
+
+    /// Names of the captured variables the step uses
+    pub fn variables(&self) -> Vec<&str> {
+        self.expressions()
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|expr| match expr {
+                Expr::Var(name) => Some(name),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Fail on templates that do not parse, variables no earlier step captures,
+    /// and bad header names or captures
+    fn check_templates(&self, captured: &HashSet<String>) -> anyhow::Result<()> {
+        for expr in self.expressions().map_err(anyhow::Error::msg)? {
+            if let Expr::Var(name) = expr {
+                if !captured.contains(name) {
+                    anyhow::bail!("uses {{{{{}}}}}, which no earlier step captures", name);
+                }
+            }
+        }
+        for name in self.headers.keys() {
+            reqwest::header::HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| anyhow::anyhow!("'{}' is not a valid header name", name))?;
+        }
+        for (name, pointer) in &self.capture {
+            if !template::is_var_name(name) {
+                anyhow::bail!(
+                    "cannot capture into '{}': names are letters, digits and '_', and not {}",
+                    name,
+                    template::FUNCTIONS.join(", ")
+                );
+            }
+            if !pointer.is_empty() && !pointer.starts_with('/') {
+                anyhow::bail!(
+                    "capture '{}' needs a JSON pointer such as \"/0/id\", got \"{}\"",
+                    name,
+                    pointer
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+#[derive(Debug, Clone)]
+pub enum Scenario {
+    /// The built-in adoption and pet food workflow
+    PetSite,
+    /// A scenario defined in a file
+    File(ScenarioDefinition),
+}
+
+impl Scenario {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|err| anyhow::anyhow!("Failed to read {}: {}", path.display(), err))?;
+        let mut definition: ScenarioDefinition = toml::from_str(&contents)
+            .map_err(|err| anyhow::anyhow!("Invalid scenario {}: {}", path.display(), err))?;
+
+        if definition.steps.is_empty() {
+            anyhow::bail!("Scenario '{}' has no steps", definition.name);
+        }
+        let mut captured = HashSet::new();
+        for step in &mut definition.steps {
+            step.method = step.method.to_uppercase();
+            if !SUPPORTED_METHODS.contains(&step.method.as_str()) {
+                anyhow::bail!(
+                    "Step '{}' uses unsupported method {} (expected one of {})",
+                    step.name,
+                    step.method,
+                    SUPPORTED_METHODS.join(", ")
+                );
+            }
+            step.check_templates(&captured)
+                .map_err(|err| anyhow::anyhow!("Step '{}': {}", step.name, err))?;
+            captured.extend(step.capture.keys().cloned());
+        }
+
+        Ok(Self::File(definition))
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Self::PetSite => "petsite",
+            Self::File(definition) => &definition.name,
+        }
+    }
+
+    /// Every service the scenario references, without duplicates
+    pub fn services(&self) -> Vec<&str> {
+        match self {
+            Self::PetSite => PETSITE_SERVICES.to_vec(),
+            Self::File(definition) => {
+                let mut services: Vec<&str> = Vec::new();
+                for step in &definition.steps {
+                    if !services.contains(&step.service.as_str()) {
+                        services.push(&step.service);
+                    }
+                }
+                services
+            }
+        }
+    }
+
+    /// Every step name the scenario records, without duplicates
+    pub fn step_names(&self) -> Vec<&str> {
+        match self {
+            Self::PetSite => PETSITE_STEPS.iter().map(|(step, _)| *step).collect(),
+            Self::File(definition) => {
+                let mut names: Vec<&str> = Vec::new();
+                for step in &definition.steps {
+                    if !names.contains(&step.name.as_str()) {
+                        names.push(&step.name);
+                    }
+                }
+                names
+            }
+        }
+    }
+
+    /// Every `(feeder, column)` pair the scenario draws from
+    pub fn feeder_references(&self) -> Vec<(String, String)> {
+        match self {
+            Self::PetSite => PETSITE_FEEDERS
+                .iter()
+                .map(|(feeder, column, _)| (feeder.to_string(), column.to_string()))
+                .collect(),
+            Self::File(definition) => definition
+                .steps
+                .iter()
+                .flat_map(|step| step.expressions().unwrap_or_default())
+                .filter_map(|expr| match expr {
+                    Expr::Feeder(feeder, column) => Some((feeder.to_string(), column.to_string())),
+                    _ => None,
+                })
+                .collect(),
+        }
+    }
+
+    /// Fail unless every referenced service has an endpoint
+    pub fn validate(&self, endpoints: &Endpoints) -> anyhow::Result<()> {
+        let missing = endpoints.missing(&self.services());
+        if !missing.is_empty() {
+            anyhow::bail!(
+                "Scenario '{}' references unresolved services: {}",
+                self.name(),
+                missing.join(", ")
+            );
+        }
+        Ok(())
+    }
+}
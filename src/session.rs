@@ -0,0 +1,273 @@
+use clap::ValueEnum;
+use colored::*;
+use reqwest::cookie::Jar;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tower::{Layer, Service};
+
+use crate::config::HttpConfig;
+use crate::types::{duration_ms, percentile};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum ConnectionMode {
+    /// One connection pool for all virtual users
+    #[default]
+    Shared,
+    /// A connection pool per virtual user, never used by another user
+    PerUser,
+    /// A new connection for every request, closed once the response is read
+    PerRequest,
+}
+
+impl ConnectionMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Shared => "shared",
+            Self::PerUser => "per_user",
+            Self::PerRequest => "per_request",
+        }
+    }
+}
+
+/// What one virtual user keeps between requests, like one browser
+pub struct Session {
+    pub client: Client,
+    /// Absent when cookies are turned off
+    pub cookies: Option<Jar>,
+}
+
+/// Every virtual user's session, created on first use and kept for the run
+/// (including its cleanup phase)
+pub struct Sessions {
+    mode: ConnectionMode,
+    cookies: bool,
+    timeout: Duration,
+    connect_timeout: Duration,
+    shared: Client,
+    users: Mutex<HashMap<String, Arc<Session>>>,
+    stats: Arc<ConnectionStats>,
+}
+
+impl Sessions {
+    pub fn new(http: &HttpConfig) -> anyhow::Result<Self> {
+        let timeout = Duration::from_secs(http.timeout_secs);
+        let connect_timeout = Duration::from_secs(http.connect_timeout_secs);
+        let stats = Arc::new(ConnectionStats::default());
+        Ok(Self {
+            mode: http.connections,
+            cookies: http.cookies,
+            timeout,
+            connect_timeout,
+            shared: build_client(http.connections, timeout, connect_timeout, &stats)?,
+            users: Mutex::new(HashMap::new()),
+            stats,
+        })
+    }
+
+    /// The user's session; fails if the user needs its own client and it can't be built
+    pub fn get(&self, user_id: &str) -> anyhow::Result<Arc<Session>> {
+        let mut users = self.users.lock().unwrap();
+        if let Some(session) = users.get(user_id) {
+            return Ok(session.clone());
+        }
+        let client = match self.mode {
+            ConnectionMode::Shared | ConnectionMode::PerRequest => self.shared.clone(),
+            // Never the shared client: that would quietly share connections
+            ConnectionMode::PerUser => {
+                build_client(self.mode, self.timeout, self.connect_timeout, &self.stats)
+                    .map_err(|err| anyhow::anyhow!("Failed to create HTTP client: {}", err))?
+            }
+        };
+        let session = Arc::new(Session {
+            client,
+            cookies: self.cookies.then(Jar::default),
+        });
+        users.insert(user_id.to_string(), session.clone());
+        Ok(session)
+    }
+
+    /// Count one HTTP exchange; each redirect hop is one
+    pub fn sent(&self) {
+        self.stats.exchanges.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn usage(&self) -> ConnectionUsage {
+        let mut connect_times = self.stats.connect_times.lock().unwrap().clone();
+        connect_times.sort();
+        let total: Duration = connect_times.iter().sum();
+        ConnectionUsage {
+            mode: self.mode,
+            opened: connect_times.len(),
+            exchanges: self.stats.exchanges.load(Ordering::Relaxed),
+            average_connect_time: total
+                .checked_div(connect_times.len() as u32)
+                .unwrap_or_default(),
+            p50_connect_time: percentile(&connect_times, 50.0),
+            p95_connect_time: percentile(&connect_times, 95.0),
+            p99_connect_time: percentile(&connect_times, 99.0),
+            max_connect_time: connect_times.last().copied().unwrap_or_default(),
+        }
+    }
+}
+
+/// Redirects are followed by the load tester, so each hop's cookies land in the user's jar
+fn build_client(
+    mode: ConnectionMode,
+    timeout: Duration,
+    connect_timeout: Duration,
+    stats: &Arc<ConnectionStats>,
+) -> reqwest::Result<Client> {
+    let mut builder = Client::builder()
+        .timeout(timeout)
+        .connect_timeout(connect_timeout)
+        .redirect(reqwest::redirect::Policy::none())
+        .connector_layer(TimeConnects(stats.clone()));
+    if mode == ConnectionMode::PerRequest {
+        // Nothing is kept idle, so no connection is ever reused
+        builder = builder.pool_max_idle_per_host(0);
+    }
+    builder.build()
+}
+
+#[derive(Default)]
+struct ConnectionStats {
+    connect_times: Mutex<Vec<Duration>>,
+    exchanges: AtomicUsize,
+}
+
+tokio::task_local! {
+    /// Connect time of the request being sent on this task
+    static CONNECTING: Arc<Mutex<Duration>>;
+}
+
+/// Run `send`, also returning how long it waited for new connections to open
+pub async fn timed_connects<F: Future>(send: F) -> (F::Output, Duration) {
+    let connect_time = Arc::new(Mutex::new(Duration::ZERO));
+    let output = CONNECTING.scope(connect_time.clone(), send).await;
+    let connect_time = *connect_time.lock().unwrap();
+    (output, connect_time)
+}
+
+/// Times every connection a client opens, from DNS lookup to the end of the TLS handshake.
+/// The connector does all three in one step, so they are timed together
+#[derive(Clone)]
+struct TimeConnects(Arc<ConnectionStats>);
+
+impl<S> Layer<S> for TimeConnects {
+    type Service = TimedConnector<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TimedConnector {
+            inner,
+            stats: self.0.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct TimedConnector<S> {
+    inner: S,
+    stats: Arc<ConnectionStats>,
+}
+
+impl<S, R> Service<R> for TimedConnector<S>
+where
+    S: Service<R>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, destination: R) -> Self::Future {
+        let stats = self.stats.clone();
+        // Called on the task of the request that needs the connection
+        let request = CONNECTING.try_with(Arc::clone).ok();
+        let started = Instant::now();
+        let connect = self.inner.call(destination);
+        Box::pin(async move {
+            let connection = connect.await?;
+            let elapsed = started.elapsed();
+            stats.connect_times.lock().unwrap().push(elapsed);
+            if let Some(request) = request {
+                *request.lock().unwrap() += elapsed;
+            }
+            Ok(connection)
+        })
+    }
+}
+
+/// Connections opened during the run, kept apart from request latency
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionUsage {
+    pub mode: ConnectionMode,
+    /// Connections established, including during the cleanup phase
+    pub opened: usize,
+    /// HTTP exchanges sent, counting each redirect hop
+    pub exchanges: usize,
+    /// DNS lookup, TCP connect and TLS handshake of each new connection, together
+    #[serde(with = "duration_ms")]
+    pub average_connect_time: Duration,
+    #[serde(with = "duration_ms")]
+    pub p50_connect_time: Duration,
+    #[serde(with = "duration_ms")]
+    pub p95_connect_time: Duration,
+    #[serde(with = "duration_ms")]
+    pub p99_connect_time: Duration,
+    #[serde(with = "duration_ms")]
+    pub max_connect_time: Duration,
+}
+
+impl ConnectionUsage {
+    /// How many exchanges each connection carried on average
+    pub fn reuse(&self) -> f64 {
+        if self.opened == 0 {
+            0.0
+        } else {
+            self.exchanges as f64 / self.opened as f64
+        }
+    }
+}
+
+pub fn display_connections(usage: &ConnectionUsage) {
+    println!(
+        "{}",
+        format!("\n🔌 Connections ({})", usage.mode.name())
+            .blue()
+            .bold()
+    );
+    println!(
+        "   {} opened for {} HTTP requests ({:.1} requests per connection)",
+        usage.opened,
+        usage.exchanges,
+        usage.reuse()
+    );
+    if usage.opened == 0 {
+        return;
+    }
+    println!(
+        "{}",
+        format!(
+            "   Connect time (DNS + TCP + TLS): {}ms avg, p50/p95/p99 {}ms / {}ms / {}ms, max {}ms",
+            usage.average_connect_time.as_millis(),
+            usage.p50_connect_time.as_millis(),
+            usage.p95_connect_time.as_millis(),
+            usage.p99_connect_time.as_millis(),
+            usage.max_connect_time.as_millis()
+        )
+        .cyan()
+    );
+}
@@ -1,170 +1,285 @@
-use aws_config::BehaviorVersion;
+use async_trait::async_trait;
+use aws_config::{BehaviorVersion, SdkConfig};
 use aws_sdk_ssm::Client;
 use colored::*;
+use futures::future::join_all;
 use std::collections::HashMap;
 
-use crate::types::Endpoints;
+use crate::endpoint_provider::EndpointProvider;
+
+/// GetParameters accepts at most 10 names per call
+const GET_PARAMETERS_BATCH_SIZE: usize = 10;
+
+pub const DEFAULT_PATH_PREFIX: &str = "/petstore/";
 
 pub struct SSMEndpointDiscovery {
     client: Client,
     service_parameters: HashMap<String, String>,
+    path_prefix: String,
+    by_path: bool,
+    fallback: bool,
 }
 
-impl SSMEndpointDiscovery {
-    pub async fn new(region: &str) -> anyhow::Result<Self> {
-        let region = aws_config::Region::new(region.to_string());
-        let config = aws_config::defaults(BehaviorVersion::latest())
-            .region(region)
-            .load()
-            .await;
-
-        let client = Client::new(&config);
+/// The AWS SDK config for `region`, loaded once and shared by SSM discovery and SigV4 signing
+pub async fn load_aws_config(region: &str) -> SdkConfig {
+    aws_config::defaults(BehaviorVersion::latest())
+        .region(aws_config::Region::new(region.to_string()))
+        .load()
+        .await
+}
 
-        // Map service names to their SSM parameter paths
+impl SSMEndpointDiscovery {
+    /// Default service name -> SSM parameter mappings for the PetSite stack.
+    /// Names without a leading `/` are resolved against the path prefix.
+    pub fn default_parameters() -> HashMap<String, String> {
         let mut service_parameters = HashMap::new();
         service_parameters.insert(
             "petlistadoptions".to_string(),
-            "/petstore/petlistadoptionsurl".to_string(),
+            "petlistadoptionsurl".to_string(),
         );
-        service_parameters.insert(
-            "petsearch".to_string(),
-            "/petstore/searchapiurl".to_string(),
-        );
-        service_parameters.insert(
-            "payforadoption".to_string(),
-            "/petstore/paymentapiurl".to_string(),
-        );
-        service_parameters.insert("petfood".to_string(), "/petstore/petfoodapiurl".to_string());
+        service_parameters.insert("petsearch".to_string(), "searchapiurl".to_string());
+        service_parameters.insert("payforadoption".to_string(), "paymentapiurl".to_string());
+        service_parameters.insert("petfood".to_string(), "petfoodapiurl".to_string());
         // Note: petfoodcarturl might be used for cart-specific operations if needed
-        service_parameters.insert(
-            "petfoodcart".to_string(),
-            "/petstore/petfoodcarturl".to_string(),
-        );
-
-        Ok(Self {
-            client,
-            service_parameters,
-        })
+        service_parameters.insert("petfoodcart".to_string(), "petfoodcarturl".to_string());
+        service_parameters
     }
 
     /// Create a new SSMEndpointDiscovery with custom service parameter mappings
-    pub async fn with_custom_parameters(
-        region: &str,
+    pub fn with_custom_parameters(
+        config: &SdkConfig,
         service_parameters: HashMap<String, String>,
     ) -> anyhow::Result<Self> {
-        let region = aws_config::Region::new(region.to_string());
-        let config = aws_config::defaults(BehaviorVersion::latest())
-            .region(region)
-            .load()
-            .await;
-
-        let client = Client::new(&config);
+        let client = Client::new(config);
 
         Ok(Self {
             client,
             service_parameters,
+            path_prefix: DEFAULT_PATH_PREFIX.to_string(),
+            by_path: false,
+            fallback: false,
         })
     }
 
-    /// Get the current service parameter mappings
-    pub fn get_service_parameters(&self) -> &HashMap<String, String> {
-        &self.service_parameters
+    /// Prefix for relative parameter names, so the same mappings work across stacks
+    pub fn with_path_prefix(mut self, prefix: &str) -> Self {
+        let trimmed = prefix.trim_end_matches('/');
+        self.path_prefix = format!("{}/", trimmed);
+        if !self.path_prefix.starts_with('/') {
+            self.path_prefix.insert(0, '/');
+        }
+        self
+    }
+
+    /// Fetch everything under the prefix with GetParametersByPath instead of GetParameters
+    pub fn with_by_path(mut self, by_path: bool) -> Self {
+        self.by_path = by_path;
+        self
     }
 
-    pub async fn discover_endpoints(&self) -> anyhow::Result<Endpoints> {
+    /// On SSM errors, warn and leave endpoints to env vars and defaults instead of failing
+    pub fn with_fallback(mut self, fallback: bool) -> Self {
+        self.fallback = fallback;
+        self
+    }
+
+    /// Full parameter name for a mapping, applying the prefix to relative names
+    fn parameter_name(&self, parameter: &str) -> String {
+        if parameter.starts_with('/') {
+            parameter.to_string()
+        } else {
+            format!("{}{}", self.path_prefix, parameter)
+        }
+    }
+
+    pub async fn discover_endpoints(&self) -> anyhow::Result<HashMap<String, String>> {
         println!("{}", "🔍 Discovering endpoints from SSM...".blue());
 
+        // Full parameter name -> services mapped to it
+        let mut wanted: HashMap<String, Vec<&str>> = HashMap::new();
+        for (service_name, parameter) in &self.service_parameters {
+            wanted
+                .entry(self.parameter_name(parameter))
+                .or_default()
+                .push(service_name);
+        }
+
+        if self.by_path {
+            let mut outside: Vec<String> = wanted
+                .extract_if(|name, _| !name.starts_with(&self.path_prefix))
+                .flat_map(|(name, services)| {
+                    services
+                        .into_iter()
+                        .map(move |service_name| format!("{} ({})", name, service_name))
+                })
+                .collect();
+            if !outside.is_empty() {
+                outside.sort();
+                println!(
+                    "{}",
+                    format!(
+                        "⚠️  Outside {}, which --ssm-by-path fetches: {}",
+                        self.path_prefix,
+                        outside.join(", ")
+                    )
+                    .yellow()
+                );
+            }
+        }
+
+        let fetched = if self.by_path {
+            self.fetch_by_path().await
+        } else {
+            self.fetch_batched(wanted.keys().cloned().collect()).await
+        };
+        let values = match fetched {
+            Ok(values) => values,
+            Err(err) if self.fallback => {
+                println!(
+                    "{}",
+                    format!("⚠️  {:#}; falling back to env vars and defaults", err).yellow()
+                );
+                return Ok(HashMap::new());
+            }
+            Err(err) => {
+                return Err(
+                    err.context("SSM discovery failed (use --ssm-fallback to continue without it)")
+                )
+            }
+        };
+
         let mut discovered = HashMap::new();
+        let mut missing = Vec::new();
+        for (name, services) in &wanted {
+            for service_name in services {
+                match values.get(name) {
+                    Some(value) => {
+                        println!("{}", format!("✓ Found {}: {}", service_name, value).green());
+                        discovered.insert(service_name.to_string(), value.clone());
+                    }
+                    None => missing.push(format!("{} ({})", name, service_name)),
+                }
+            }
+        }
 
-        // Iterate through service name -> SSM parameter mappings
-        for (service_name, parameter_path) in &self.service_parameters {
-            match self
-                .client
-                .get_parameter()
-                .name(parameter_path)
-                .with_decryption(true)
-                .send()
-                .await
-            {
-                Ok(result) => {
-                    if let Some(parameter) = result.parameter {
-                        if let Some(value) = parameter.value {
-                            discovered.insert(service_name.clone(), value.clone());
-                            println!("{}", format!("✓ Found {}: {}", service_name, value).green());
+        if !missing.is_empty() {
+            missing.sort();
+            println!(
+                "{}",
+                format!("⚠️  No SSM value for: {}", missing.join(", ")).yellow()
+            );
+        }
+
+        Ok(discovered)
+    }
+
+    /// Look up the given names with concurrent GetParameters calls of up to 10 names each
+    async fn fetch_batched(
+        &self,
+        mut names: Vec<String>,
+    ) -> anyhow::Result<HashMap<String, String>> {
+        names.sort();
+        let batches = names
+            .chunks(GET_PARAMETERS_BATCH_SIZE)
+            .map(|batch| async move {
+                let result = self
+                    .client
+                    .get_parameters()
+                    .set_names(Some(batch.to_vec()))
+                    .with_decryption(true)
+                    .send()
+                    .await;
+                (batch, result)
+            });
+
+        let mut values = HashMap::new();
+        let mut errors = Vec::new();
+        for (batch, result) in join_all(batches).await {
+            match result {
+                Ok(output) => {
+                    for parameter in output.parameters() {
+                        if let (Some(name), Some(value)) = (parameter.name(), parameter.value()) {
+                            values.insert(name.to_string(), value.to_string());
                         }
                     }
-                }
-                Err(err) => {
-                    if err.to_string().contains("ParameterNotFound") {
+                    if !output.invalid_parameters().is_empty() {
                         println!(
                             "{}",
                             format!(
-                                "⚠️  Parameter not found: {} ({})",
-                                service_name, parameter_path
+                                "⚠️  Invalid parameters: {}",
+                                output.invalid_parameters().join(", ")
                             )
                             .yellow()
                         );
-                    } else {
-                        println!(
-                            "{}",
-                            format!(
-                                "❌ Error fetching {} ({}): {}",
-                                service_name, parameter_path, err
-                            )
-                            .red()
-                        );
                     }
                 }
+                Err(err) => errors.push(format!(
+                    "Error fetching {}: {}",
+                    batch.join(", "),
+                    aws_sdk_ssm::error::DisplayErrorContext(&err)
+                )),
             }
         }
+        if !errors.is_empty() {
+            anyhow::bail!(errors.join("; "));
+        }
+        Ok(values)
+    }
 
-        // Create endpoints with discovered values or fallbacks
-        let mut endpoints = Endpoints::new();
+    /// Fetch every parameter under the path prefix, following pagination
+    async fn fetch_by_path(&self) -> anyhow::Result<HashMap<String, String>> {
+        let path = match self.path_prefix.trim_end_matches('/') {
+            "" => "/",
+            trimmed => trimmed,
+        };
 
-        if let Some(endpoint) = discovered.get("petlistadoptions") {
-            endpoints.petlistadoptions = endpoint.clone();
-        } else {
-            println!(
-                "{}",
-                format!(
-                    "🔄 Using fallback for petlistadoptions: {}",
-                    endpoints.petlistadoptions
-                )
-                .cyan()
-            );
-        }
+        let mut values = HashMap::new();
+        let mut next_token = None;
+        loop {
+            let output = self
+                .client
+                .get_parameters_by_path()
+                .path(path)
+                .recursive(true)
+                .with_decryption(true)
+                .set_next_token(next_token)
+                .send()
+                .await
+                .map_err(|err| {
+                    anyhow::anyhow!(
+                        "Error fetching parameters under {}: {}",
+                        path,
+                        aws_sdk_ssm::error::DisplayErrorContext(&err)
+                    )
+                })?;
 
-        if let Some(endpoint) = discovered.get("petsearch") {
-            endpoints.petsearch = endpoint.clone();
-        } else {
-            println!(
-                "{}",
-                format!("🔄 Using fallback for petsearch: {}", endpoints.petsearch).cyan()
-            );
-        }
+            for parameter in output.parameters() {
+                if let (Some(name), Some(value)) = (parameter.name(), parameter.value()) {
+                    values.insert(name.to_string(), value.to_string());
+                }
+            }
 
-        if let Some(endpoint) = discovered.get("payforadoption") {
-            endpoints.payforadoption = endpoint.clone();
-        } else {
-            println!(
-                "{}",
-                format!(
-                    "🔄 Using fallback for payforadoption: {}",
-                    endpoints.payforadoption
-                )
-                .cyan()
-            );
+            match output.next_token() {
+                Some(token) => next_token = Some(token.to_string()),
+                None => break,
+            }
         }
 
-        if let Some(endpoint) = discovered.get("petfood") {
-            endpoints.petfood = endpoint.clone();
-        } else {
-            println!(
-                "{}",
-                format!("🔄 Using fallback for petfood: {}", endpoints.petfood).cyan()
-            );
-        }
+        println!(
+            "{}",
+            format!("📦 Fetched {} parameters under {}", values.len(), path).bright_black()
+        );
+        Ok(values)
+    }
+}
+
+#[async_trait]
+impl EndpointProvider for SSMEndpointDiscovery {
+    fn name(&self) -> &str {
+        "ssm"
+    }
 
-        Ok(endpoints)
+    async fn discover(&self) -> anyhow::Result<HashMap<String, String>> {
+        self.discover_endpoints().await
     }
 }
@@ -0,0 +1,391 @@
+use rand::Rng;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::payment::civil_from_days;
+
+/// Built-in functions; the names cannot be used for captured variables
+pub const FUNCTIONS: [&str; 4] = ["user_id", "uuid", "random_int", "now_iso"];
+
+/// Values captured from earlier responses of one scenario run, by name
+pub type Vars = HashMap<String, serde_json::Value>;
+
+/// One `{{...}}` expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr<'a> {
+    /// `{{user_id}}`: the virtual user's id
+    UserId,
+    /// `{{uuid}}`: a fresh v4 UUID
+    Uuid,
+    /// `{{random_int A B}}`: an integer from A to B, inclusive
+    RandomInt(i64, i64),
+    /// `{{now_iso}}`: the current UTC time, e.g. `2024-05-01T12:00:00.000Z`
+    NowIso,
+    /// `{{feeder.column}}`: a value from a feeder row
+    Feeder(&'a str, &'a str),
+    /// `{{name}}`: a variable captured from an earlier response
+    Var(&'a str),
+}
+
+impl<'a> Expr<'a> {
+    pub fn parse(text: &'a str) -> Result<Self, String> {
+        let mut words = text.split_whitespace();
+        let name = words.next().ok_or("{{}} is empty")?;
+        let args: Vec<&str> = words.collect();
+        let expr = match name {
+            "user_id" => Self::UserId,
+            "uuid" => Self::Uuid,
+            "now_iso" => Self::NowIso,
+            "random_int" => {
+                let usage = "random_int takes two integers, e.g. {{random_int 1 10}}";
+                let [low, high] = args[..] else {
+                    return Err(usage.to_string());
+                };
+                let (Ok(low), Ok(high)) = (low.parse::<i64>(), high.parse::<i64>()) else {
+                    return Err(usage.to_string());
+                };
+                if low > high {
+                    return Err(format!(
+                        "random_int {} {} has its bounds the wrong way round",
+                        low, high
+                    ));
+                }
+                return Ok(Self::RandomInt(low, high));
+            }
+            _ => match name.split_once('.') {
+                Some((feeder, column)) if !feeder.is_empty() && !column.is_empty() => {
+                    Self::Feeder(feeder, column)
+                }
+                None if is_var_name(name) => Self::Var(name),
+                _ => {
+                    return Err(format!(
+                        "'{}' is not a function, feeder.column or variable",
+                        name
+                    ))
+                }
+            },
+        };
+        if !args.is_empty() {
+            return Err(format!("{} takes no arguments", name));
+        }
+        Ok(expr)
+    }
+
+    /// The value of a built-in function; `None` for feeder columns and variables
+    pub fn function(&self, user_id: &str, rng: &mut impl Rng) -> Option<serde_json::Value> {
+        match *self {
+            Self::UserId => Some(user_id.into()),
+            Self::Uuid => Some(
+                uuid::Builder::from_random_bytes(rng.gen())
+                    .into_uuid()
+                    .to_string()
+                    .into(),
+            ),
+            Self::RandomInt(low, high) => Some(rng.gen_range(low..=high).into()),
+            Self::NowIso => Some(now_iso().into()),
+            Self::Feeder(..) | Self::Var(_) => None,
+        }
+    }
+}
+
+/// Whether `name` can name a captured variable
+pub fn is_var_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !FUNCTIONS.contains(&name)
+}
+
+/// A function or a captured variable; feeder columns are left to the caller
+pub fn resolve(
+    expr: &Expr,
+    user_id: &str,
+    vars: &Vars,
+    rng: &mut impl Rng,
+) -> Option<serde_json::Value> {
+    match expr {
+        Expr::Var(name) => vars.get(*name).cloned(),
+        _ => expr.function(user_id, rng),
+    }
+}
+
+/// Literal text and the expressions between `{{` and `}}`, as written
+enum Piece<'a> {
+    Text(&'a str),
+    Expr { raw: &'a str, inner: &'a str },
+}
+
+fn pieces(text: &str) -> Vec<Piece<'_>> {
+    let mut pieces = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        if start > 0 {
+            pieces.push(Piece::Text(&rest[..start]));
+        }
+        pieces.push(Piece::Expr {
+            raw: &rest[start..start + end + 2],
+            inner: rest[start + 2..start + end].trim(),
+        });
+        rest = &rest[start + end + 2..];
+    }
+    if !rest.is_empty() {
+        pieces.push(Piece::Text(rest));
+    }
+    pieces
+}
+
+/// Every expression in `text`, or why the first bad one does not parse
+pub fn expressions(text: &str) -> Result<Vec<Expr<'_>>, String> {
+    pieces(text)
+        .into_iter()
+        .filter_map(|piece| match piece {
+            Piece::Expr { inner, .. } => Some(Expr::parse(inner)),
+            Piece::Text(_) => None,
+        })
+        .collect()
+}
+
+/// Every expression in the strings of a JSON value, appended to `found`
+pub fn json_expressions<'a>(
+    value: &'a serde_json::Value,
+    found: &mut Vec<Expr<'a>>,
+) -> Result<(), String> {
+    match value {
+        serde_json::Value::String(text) => found.extend(expressions(text)?),
+        serde_json::Value::Array(items) => {
+            for item in items {
+                json_expressions(item, found)?;
+            }
+        }
+        serde_json::Value::Object(fields) => {
+            for item in fields.values() {
+                json_expressions(item, found)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Replace each `{{expression}}` in `text` that `lookup` has a value for; the
+/// rest are left as written
+pub fn render(text: &str, lookup: &mut impl FnMut(&Expr) -> Option<serde_json::Value>) -> String {
+    let mut output = String::with_capacity(text.len());
+    for piece in pieces(text) {
+        match piece {
+            Piece::Text(text) => output.push_str(text),
+            Piece::Expr { raw, inner } => {
+                match Expr::parse(inner).ok().and_then(|expr| lookup(&expr)) {
+                    Some(value) => output.push_str(&to_text(&value)),
+                    None => output.push_str(raw),
+                }
+            }
+        }
+    }
+    output
+}
+
+/// `render` applied to every string inside a JSON value. A string that is a
+/// single expression takes the value's type, so `"{{random_int 1 5}}"` sends a number.
+pub fn render_json(
+    value: &serde_json::Value,
+    lookup: &mut impl FnMut(&Expr) -> Option<serde_json::Value>,
+) -> serde_json::Value {
+    match value {
+        serde_json::Value::String(text) => {
+            if let [Piece::Expr { inner, .. }] = pieces(text)[..] {
+                if let Some(value) = Expr::parse(inner).ok().and_then(|expr| lookup(&expr)) {
+                    return value;
+                }
+            }
+            serde_json::Value::String(render(text, lookup))
+        }
+        serde_json::Value::Array(items) => {
+            serde_json::Value::Array(items.iter().map(|item| render_json(item, lookup)).collect())
+        }
+        serde_json::Value::Object(fields) => serde_json::Value::Object(
+            fields
+                .iter()
+                .map(|(key, item)| (key.clone(), render_json(item, lookup)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// The value at `pointer` (RFC 6901, e.g. `/0/petid`) in a JSON response body
+pub fn capture(body: &str, pointer: &str) -> Option<serde_json::Value> {
+    serde_json::from_str::<serde_json::Value>(body)
+        .ok()?
+        .pointer(pointer)
+        .filter(|value| !value.is_null())
+        .cloned()
+}
+
+/// Strings go in unquoted, everything else as JSON
+fn to_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// The current UTC time with milliseconds, e.g. `2024-05-01T12:00:00.000Z`
+fn now_iso() -> String {
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let secs = elapsed.as_secs() as i64;
+    let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+    let time = secs.rem_euclid(86_400);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60,
+        elapsed.subsec_millis()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use serde_json::json;
+
+    fn lookup(expr: &Expr) -> Option<serde_json::Value> {
+        let vars = Vars::from([
+            ("pet_id".to_string(), json!("p-1")),
+            ("count".to_string(), json!(3)),
+        ]);
+        let mut rng = StdRng::seed_from_u64(1);
+        resolve(expr, "user-1", &vars, &mut rng)
+    }
+
+    #[test]
+    fn parses_each_kind_of_expression() {
+        assert_eq!(Expr::parse("user_id"), Ok(Expr::UserId));
+        assert_eq!(Expr::parse("uuid"), Ok(Expr::Uuid));
+        assert_eq!(Expr::parse("now_iso"), Ok(Expr::NowIso));
+        assert_eq!(Expr::parse("random_int -5 10"), Ok(Expr::RandomInt(-5, 10)));
+        assert_eq!(
+            Expr::parse("products.sku"),
+            Ok(Expr::Feeder("products", "sku"))
+        );
+        assert_eq!(Expr::parse("pet_id"), Ok(Expr::Var("pet_id")));
+        assert_eq!(Expr::parse("_id2"), Ok(Expr::Var("_id2")));
+    }
+
+    #[test]
+    fn rejects_bad_expressions() {
+        for text in [
+            "",
+            "random_int",
+            "random_int 1",
+            "random_int 1 x",
+            "random_int 10 1",
+            "uuid 4",
+            "products.",
+            ".sku",
+            "2fast",
+            "pet-id",
+        ] {
+            assert!(Expr::parse(text).is_err(), "'{}' parsed", text);
+        }
+    }
+
+    #[test]
+    fn function_names_are_not_variables() {
+        for name in FUNCTIONS {
+            assert!(!is_var_name(name), "{}", name);
+        }
+        assert!(is_var_name("user"));
+    }
+
+    #[test]
+    fn finds_expressions_between_braces() {
+        assert_eq!(
+            expressions("/pets/{{ pet_id }}?u={{user_id}}&t={{feed.term}}"),
+            Ok(vec![
+                Expr::Var("pet_id"),
+                Expr::UserId,
+                Expr::Feeder("feed", "term")
+            ])
+        );
+        assert_eq!(expressions("no braces {{ here"), Ok(Vec::new()));
+        assert!(expressions("/a/{{random_int 2 1}}").is_err());
+    }
+
+    #[test]
+    fn renders_known_values_and_keeps_the_rest() {
+        assert_eq!(
+            render("/pets/{{pet_id}}/{{count}}?u={{user_id}}", &mut lookup),
+            "/pets/p-1/3?u=user-1"
+        );
+        assert_eq!(
+            render("{{missing}} {{feed.term}} {{bad expr}} {{open", &mut lookup),
+            "{{missing}} {{feed.term}} {{bad expr}} {{open"
+        );
+    }
+
+    #[test]
+    fn random_int_stays_in_bounds() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..100 {
+            let value = Expr::RandomInt(3, 5).function("u", &mut rng).unwrap();
+            assert!((3..=5).contains(&value.as_i64().unwrap()));
+        }
+    }
+
+    #[test]
+    fn render_json_keeps_the_type_of_a_lone_expression() {
+        let body = json!({
+            "count": "{{count}}",
+            "label": "x{{count}}",
+            "items": ["{{pet_id}}", 7, null],
+            "unknown": "{{missing}}"
+        });
+        assert_eq!(
+            render_json(&body, &mut lookup),
+            json!({
+                "count": 3,
+                "label": "x3",
+                "items": ["p-1", 7, null],
+                "unknown": "{{missing}}"
+            })
+        );
+    }
+
+    #[test]
+    fn json_expressions_walk_nested_values() {
+        let body = json!({"a": ["{{user_id}}", {"b": "{{pet_id}}"}], "c": 1});
+        let mut found = Vec::new();
+        json_expressions(&body, &mut found).unwrap();
+        assert_eq!(found.len(), 2);
+        assert!(found.contains(&Expr::UserId));
+        assert!(found.contains(&Expr::Var("pet_id")));
+    }
+
+    #[test]
+    fn captures_non_null_values_by_pointer() {
+        let body = r#"[{"petid": "p-7", "color": null}]"#;
+        assert_eq!(capture(body, "/0/petid"), Some(json!("p-7")));
+        assert_eq!(capture(body, "/0/color"), None);
+        assert_eq!(capture(body, "/1/petid"), None);
+        assert_eq!(capture("not json", "/0"), None);
+    }
+
+    #[test]
+    fn now_iso_has_the_expected_shape() {
+        let now = now_iso();
+        assert_eq!(now.len(), 24, "{}", now);
+        assert!(now.ends_with('Z'));
+        assert_eq!(&now[10..11], "T");
+    }
+}
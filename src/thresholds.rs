@@ -0,0 +1,252 @@
+use colored::*;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+
+use crate::types::{LoadTestResults, StepStats};
+
+/// Pass/fail limits for one scope (the whole run or a single step)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Limits {
+    /// Maximum percentage of failed requests
+    pub max_error_rate: Option<f64>,
+    pub max_avg_ms: Option<f64>,
+    pub max_p95_ms: Option<f64>,
+    pub max_p99_ms: Option<f64>,
+    pub min_rps: Option<f64>,
+}
+
+/// Thresholds checked after the run:
+///
+/// ```toml
+/// [thresholds]
+/// max_error_rate = 1.0
+/// max_p95_ms = 800
+///
+/// [thresholds.steps.checkout]
+/// max_p95_ms = 1500
+/// ```
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Thresholds {
+    #[serde(flatten)]
+    pub run: Limits,
+    pub steps: BTreeMap<String, Limits>,
+}
+
+// Written by hand because serde can't deny unknown fields next to `flatten`:
+// the run limits are read apart from `steps`, so a misspelled limit fails
+impl<'de> Deserialize<'de> for Thresholds {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut table = serde_json::Map::<String, serde_json::Value>::deserialize(deserializer)?;
+        let steps = match table.remove("steps") {
+            Some(steps) => serde_json::from_value(steps)
+                .map_err(|err| D::Error::custom(format!("steps: {}", err)))?,
+            None => BTreeMap::new(),
+        };
+        let run = serde_json::from_value(table.into()).map_err(D::Error::custom)?;
+        Ok(Self { run, steps })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThresholdResult {
+    /// `run` or `step:<name>`
+    pub scope: String,
+    pub metric: String,
+    pub limit: f64,
+    /// `None` when the scope produced no data (e.g. the step never ran)
+    pub observed: Option<f64>,
+    pub passed: bool,
+}
+
+/// Observed values for one scope (the whole run or a step)
+struct Observed {
+    error_rate: f64,
+    avg_ms: f64,
+    p95_ms: f64,
+    p99_ms: f64,
+    rps: f64,
+}
+
+impl Observed {
+    fn from_step(step: &StepStats) -> Self {
+        Self {
+            error_rate: step.error_rate,
+            avg_ms: step.average_response_time.as_secs_f64() * 1000.0,
+            p95_ms: step.p95_response_time.as_secs_f64() * 1000.0,
+            p99_ms: step.p99_response_time.as_secs_f64() * 1000.0,
+            rps: step.requests_per_second,
+        }
+    }
+
+    fn from_run(results: &LoadTestResults) -> Self {
+        Self {
+            error_rate: if results.total_requests > 0 {
+                100.0 - results.success_rate
+            } else {
+                0.0
+            },
+            avg_ms: results.average_response_time.as_secs_f64() * 1000.0,
+            p95_ms: results.p95_response_time.as_secs_f64() * 1000.0,
+            p99_ms: results.p99_response_time.as_secs_f64() * 1000.0,
+            rps: results.requests_per_second,
+        }
+    }
+}
+
+impl Limits {
+    fn count(&self) -> usize {
+        [
+            self.max_error_rate,
+            self.max_avg_ms,
+            self.max_p95_ms,
+            self.max_p99_ms,
+            self.min_rps,
+        ]
+        .iter()
+        .filter(|limit| limit.is_some())
+        .count()
+    }
+
+    fn check(&self, scope: &str, observed: Option<&Observed>) -> Vec<ThresholdResult> {
+        let checks = [
+            (
+                "error_rate",
+                self.max_error_rate,
+                observed.map(|o| o.error_rate),
+                true,
+            ),
+            ("avg_ms", self.max_avg_ms, observed.map(|o| o.avg_ms), true),
+            ("p95_ms", self.max_p95_ms, observed.map(|o| o.p95_ms), true),
+            ("p99_ms", self.max_p99_ms, observed.map(|o| o.p99_ms), true),
+            ("rps", self.min_rps, observed.map(|o| o.rps), false),
+        ];
+
+        checks
+            .into_iter()
+            .filter_map(|(metric, limit, value, is_max)| {
+                let limit = limit?;
+                let passed = match value {
+                    Some(value) if is_max => value <= limit,
+                    Some(value) => value >= limit,
+                    None => false,
+                };
+                Some(ThresholdResult {
+                    scope: scope.to_string(),
+                    metric: metric.to_string(),
+                    limit,
+                    observed: value,
+                    passed,
+                })
+            })
+            .collect()
+    }
+}
+
+impl Thresholds {
+    /// Number of configured limits across the run and every step
+    pub fn count(&self) -> usize {
+        self.run.count() + self.steps.values().map(Limits::count).sum::<usize>()
+    }
+
+    pub fn evaluate(&self, results: &LoadTestResults) -> Vec<ThresholdResult> {
+        let mut outcomes = self.run.check("run", Some(&Observed::from_run(results)));
+
+        for (step, limits) in &self.steps {
+            let observed = results.step(step).map(Observed::from_step);
+            outcomes.extend(limits.check(&format!("step:{}", step), observed.as_ref()));
+        }
+
+        outcomes
+    }
+}
+
+impl ThresholdResult {
+    /// Comparison operator for display, e.g. `<=` for maximums
+    pub fn operator(&self) -> &'static str {
+        if self.metric == "rps" {
+            ">="
+        } else {
+            "<="
+        }
+    }
+}
+
+pub fn display_thresholds(outcomes: &[ThresholdResult]) {
+    if outcomes.is_empty() {
+        return;
+    }
+
+    println!("{}", "\n🎯 Thresholds".blue().bold());
+    for outcome in outcomes {
+        let observed = outcome
+            .observed
+            .map(|value| format!("{:.1}", value))
+            .unwrap_or_else(|| "no data".to_string());
+        let line = format!(
+            "{} {}: {} (limit {} {})",
+            outcome.scope,
+            outcome.metric,
+            observed,
+            outcome.operator(),
+            outcome.limit
+        );
+        if outcome.passed {
+            println!("{}", format!("  ✓ {}", line).green());
+        } else {
+            println!("{}", format!("  ✗ {}", line).red());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_run_and_step_limits() {
+        let thresholds: Thresholds = toml::from_str(
+            "max_error_rate = 1.0\nmax_p95_ms = 800\n\n[steps.checkout]\nmax_p95_ms = 1500\n",
+        )
+        .unwrap();
+        assert_eq!(thresholds.run.max_error_rate, Some(1.0));
+        assert_eq!(thresholds.run.max_p95_ms, Some(800.0));
+        assert_eq!(thresholds.steps["checkout"].max_p95_ms, Some(1500.0));
+        assert_eq!(thresholds.count(), 3);
+    }
+
+    #[test]
+    fn misspelled_run_limit_is_an_error() {
+        let err = toml::from_str::<Thresholds>("max_p95ms = 800\n").unwrap_err();
+        assert!(err.to_string().contains("max_p95ms"), "{}", err);
+    }
+
+    #[test]
+    fn misspelled_step_limit_is_an_error() {
+        let err = toml::from_str::<Thresholds>("[steps.checkout]\nmax_p95ms = 800\n").unwrap_err();
+        assert!(err.to_string().contains("max_p95ms"), "{}", err);
+    }
+
+    #[test]
+    fn failing_limits_are_reported() {
+        let limits = Limits {
+            max_p95_ms: Some(100.0),
+            min_rps: Some(10.0),
+            ..Limits::default()
+        };
+        let observed = Observed {
+            error_rate: 0.0,
+            avg_ms: 50.0,
+            p95_ms: 150.0,
+            p99_ms: 200.0,
+            rps: 20.0,
+        };
+        let outcomes = limits.check("run", Some(&observed));
+        assert_eq!(outcomes.len(), 2);
+        assert!(!outcomes[0].passed);
+        assert!(outcomes[1].passed);
+        let missing = limits.check("step:x", None);
+        assert!(missing.iter().all(|outcome| !outcome.passed));
+    }
+}
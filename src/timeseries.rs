@@ -0,0 +1,465 @@
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::types::{duration_ms, percentile, RequestResult, UserScenarioResult};
+
+/// Aim for about this many windows when no width is configured
+const TARGET_WINDOWS: u32 = 60;
+
+/// p95 this many times the early-run level marks a latency knee
+const LATENCY_KNEE_FACTOR: f64 = 1.5;
+
+/// Error rate this many percentage points above the early-run level marks an error knee
+const ERROR_KNEE_INCREASE: f64 = 5.0;
+
+/// A knee must hold for this many consecutive windows, so one slow window is not enough
+const KNEE_SUSTAIN_WINDOWS: usize = 2;
+
+/// Metrics for the requests that started within one window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeWindow {
+    /// Window start, relative to the start of the run
+    #[serde(with = "duration_ms")]
+    pub start: Duration,
+    pub requests: usize,
+    pub errors: usize,
+    /// Percentage of requests that failed
+    pub error_rate: f64,
+    pub requests_per_second: f64,
+    /// Virtual users with a scenario in progress at any point in the window
+    pub active_users: usize,
+    #[serde(with = "duration_ms")]
+    pub p50_response_time: Duration,
+    #[serde(with = "duration_ms")]
+    pub p95_response_time: Duration,
+    #[serde(with = "duration_ms")]
+    pub p99_response_time: Duration,
+}
+
+/// The windows for one scenario step
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepSeries {
+    pub step: String,
+    pub windows: Vec<TimeWindow>,
+}
+
+/// The point where a metric started rising away from its early-run level
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Knee {
+    /// `p95_ms` or `error_rate`
+    pub metric: String,
+    /// Start of the first degraded window
+    #[serde(with = "duration_ms")]
+    pub at: Duration,
+    pub active_users: usize,
+    /// Median over the early windows
+    pub baseline: f64,
+    pub value: f64,
+}
+
+/// Run metrics bucketed into fixed windows, for the whole run and per step
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TimeSeries {
+    #[serde(with = "duration_ms")]
+    pub window: Duration,
+    pub run: Vec<TimeWindow>,
+    /// Per-step windows, in the order steps first ran
+    pub steps: Vec<StepSeries>,
+    pub knees: Vec<Knee>,
+}
+
+impl TimeWindow {
+    fn from_requests(
+        start: Duration,
+        width: Duration,
+        requests: &[&RequestResult],
+        active_users: usize,
+    ) -> Self {
+        let errors = requests.iter().filter(|r| !r.success).count();
+        let mut response_times: Vec<Duration> = requests
+            .iter()
+            .filter(|r| r.success)
+            .map(|r| r.response_time)
+            .collect();
+        response_times.sort();
+
+        Self {
+            start,
+            requests: requests.len(),
+            errors,
+            error_rate: if requests.is_empty() {
+                0.0
+            } else {
+                (errors as f64 / requests.len() as f64) * 100.0
+            },
+            requests_per_second: requests.len() as f64 / width.as_secs_f64(),
+            active_users,
+            p50_response_time: percentile(&response_times, 50.0),
+            p95_response_time: percentile(&response_times, 95.0),
+            p99_response_time: percentile(&response_times, 99.0),
+        }
+    }
+}
+
+/// A whole-second window width giving roughly `TARGET_WINDOWS` windows
+pub fn auto_width(total_time: Duration) -> Duration {
+    Duration::from_secs(total_time.as_secs().div_ceil(TARGET_WINDOWS.into()).max(1))
+}
+
+/// Bucket `requests` by start offset into `active_users.len()` windows of `width`
+fn bucket(requests: &[&RequestResult], width: Duration, active_users: &[usize]) -> Vec<TimeWindow> {
+    let window_count = active_users.len();
+    let mut buckets: Vec<Vec<&RequestResult>> = vec![Vec::new(); window_count];
+    for request in requests {
+        let index = (request.started_at.as_secs_f64() / width.as_secs_f64()) as usize;
+        buckets[index.min(window_count - 1)].push(request);
+    }
+
+    buckets
+        .iter()
+        .zip(active_users)
+        .enumerate()
+        .map(|(index, (requests, &users))| {
+            TimeWindow::from_requests(width * index as u32, width, requests, users)
+        })
+        .collect()
+}
+
+/// Users whose scenario (first request sent to last response) overlaps each window
+fn active_users(
+    results: &[UserScenarioResult],
+    width: Duration,
+    window_count: usize,
+) -> Vec<usize> {
+    let mut active = vec![0; window_count];
+    for scenario in results {
+        let Some(first) = scenario.requests.iter().map(|r| r.started_at).min() else {
+            continue;
+        };
+        let last = scenario
+            .requests
+            .iter()
+            .map(|r| r.started_at + r.response_time)
+            .max()
+            .unwrap_or(first);
+        let from = (first.as_secs_f64() / width.as_secs_f64()) as usize;
+        let to = (last.as_secs_f64() / width.as_secs_f64()) as usize;
+        for count in active
+            .iter_mut()
+            .take(to.min(window_count - 1) + 1)
+            .skip(from)
+        {
+            *count += 1;
+        }
+    }
+    active
+}
+
+fn median(values: &mut [f64]) -> f64 {
+    values.sort_by(f64::total_cmp);
+    values[values.len() / 2]
+}
+
+/// First window where `value` crosses `limit(baseline)` and stays there for
+/// `KNEE_SUSTAIN_WINDOWS` windows. The early-run baseline is the median of the
+/// first fifth of busy windows (at least three).
+fn find_knee(
+    metric: &str,
+    windows: &[TimeWindow],
+    value: impl Fn(&TimeWindow) -> f64,
+    limit: impl Fn(f64) -> f64,
+) -> Option<Knee> {
+    let busy: Vec<&TimeWindow> = windows.iter().filter(|w| w.requests > 0).collect();
+    let early = (busy.len() / 5).max(3);
+    if busy.len() < early + KNEE_SUSTAIN_WINDOWS {
+        return None;
+    }
+
+    let mut early_values: Vec<f64> = busy[..early].iter().map(|w| value(w)).collect();
+    let baseline = median(&mut early_values);
+    let limit = limit(baseline);
+
+    busy[early..]
+        .windows(KNEE_SUSTAIN_WINDOWS)
+        .find(|run| run.iter().all(|w| value(w) > limit))
+        .map(|run| Knee {
+            metric: metric.to_string(),
+            at: run[0].start,
+            active_users: run[0].active_users,
+            baseline,
+            value: value(run[0]),
+        })
+}
+
+impl TimeSeries {
+    /// Bucket every request into windows of `window` (or an automatic width)
+    pub fn from_scenarios(
+        results: &[UserScenarioResult],
+        total_time: Duration,
+        window: Option<Duration>,
+    ) -> Self {
+        let width = window.unwrap_or_else(|| auto_width(total_time));
+        let requests: Vec<&RequestResult> = results.iter().flat_map(|r| &r.requests).collect();
+        let Some(last) = requests.iter().map(|r| r.started_at).max() else {
+            return Self {
+                window: width,
+                ..Self::default()
+            };
+        };
+
+        let window_count = (last.as_secs_f64() / width.as_secs_f64()) as usize + 1;
+        let active = active_users(results, width, window_count);
+
+        let mut step_order: Vec<&str> = Vec::new();
+        for request in &requests {
+            if !step_order.contains(&request.step.as_str()) {
+                step_order.push(&request.step);
+            }
+        }
+        let steps = step_order
+            .iter()
+            .map(|step| {
+                let step_requests: Vec<&RequestResult> = requests
+                    .iter()
+                    .filter(|r| r.step == *step)
+                    .copied()
+                    .collect();
+                StepSeries {
+                    step: step.to_string(),
+                    windows: bucket(&step_requests, width, &active),
+                }
+            })
+            .collect();
+
+        let run = bucket(&requests, width, &active);
+        let knees = [
+            find_knee(
+                "p95_ms",
+                &run,
+                |w| w.p95_response_time.as_secs_f64() * 1000.0,
+                |baseline| baseline * LATENCY_KNEE_FACTOR,
+            ),
+            find_knee(
+                "error_rate",
+                &run,
+                |w| w.error_rate,
+                |baseline| baseline + ERROR_KNEE_INCREASE,
+            ),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        Self {
+            window: width,
+            run,
+            steps,
+            knees,
+        }
+    }
+}
+
+/// When each stage sent its first request, in run order
+pub fn stage_starts(results: &[UserScenarioResult]) -> Vec<(String, Duration)> {
+    let mut starts: Vec<(String, Duration)> = Vec::new();
+    for scenario in results {
+        let Some(first) = scenario.requests.iter().map(|r| r.started_at).min() else {
+            continue;
+        };
+        match starts
+            .iter_mut()
+            .find(|(stage, _)| *stage == scenario.stage)
+        {
+            Some((_, start)) => *start = (*start).min(first),
+            None => starts.push((scenario.stage.clone(), first)),
+        }
+    }
+    starts.sort_by_key(|(_, start)| *start);
+    starts
+}
+
+pub fn display_timeseries(series: &TimeSeries, verbose: bool) {
+    for knee in &series.knees {
+        let (unit, label) = if knee.metric == "error_rate" {
+            ("%", "Error rate")
+        } else {
+            ("ms", "p95 latency")
+        };
+        println!(
+            "{}",
+            format!(
+                "📉 {} knee at {:.0}s with {} active users: {:.1}{} → {:.1}{}",
+                label,
+                knee.at.as_secs_f64(),
+                knee.active_users,
+                knee.baseline,
+                unit,
+                knee.value,
+                unit
+            )
+            .yellow()
+        );
+    }
+
+    if !verbose || series.run.is_empty() {
+        return;
+    }
+
+    println!(
+        "{}",
+        format!(
+            "\n⏱️  Over Time ({}s windows):",
+            series.window.as_secs_f64()
+        )
+        .blue()
+        .bold()
+    );
+    println!("{}", "─".repeat(80).purple());
+    println!(
+        "{}",
+        format!(
+            "  {:>7} {:>6} {:>8} {:>8} {:>8} {:>8} {:>8}",
+            "START", "USERS", "REQS", "ERR%", "P50", "P95", "P99"
+        )
+        .bright_black()
+    );
+    for window in &series.run {
+        println!(
+            "  {:>6.0}s {:>6} {:>8} {:>7.1}% {:>6}ms {:>6}ms {:>6}ms",
+            window.start.as_secs_f64(),
+            window.active_users,
+            window.requests,
+            window.error_rate,
+            window.p50_response_time.as_millis(),
+            window.p95_response_time.as_millis(),
+            window.p99_response_time.as_millis()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Busy one-second windows with the given p95 (ms) and error rate (%)
+    fn windows(values: &[(u64, f64)]) -> Vec<TimeWindow> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(index, &(p95, error_rate))| TimeWindow {
+                start: Duration::from_secs(index as u64),
+                requests: 100,
+                errors: error_rate as usize,
+                error_rate,
+                requests_per_second: 100.0,
+                active_users: index + 1,
+                p50_response_time: Duration::from_millis(p95 / 2),
+                p95_response_time: Duration::from_millis(p95),
+                p99_response_time: Duration::from_millis(p95 * 2),
+            })
+            .collect()
+    }
+
+    fn latency_knee(windows: &[TimeWindow]) -> Option<Knee> {
+        find_knee(
+            "p95_ms",
+            windows,
+            |w| w.p95_response_time.as_secs_f64() * 1000.0,
+            |baseline| baseline * LATENCY_KNEE_FACTOR,
+        )
+    }
+
+    #[test]
+    fn finds_a_sustained_latency_knee() {
+        let p95s = [100, 110, 90, 100, 120, 200, 210, 250];
+        let windows = windows(&p95s.map(|p95| (p95, 0.0)));
+        let knee = latency_knee(&windows).unwrap();
+        assert_eq!(knee.at, Duration::from_secs(5));
+        assert_eq!(knee.active_users, 6);
+        assert_eq!(knee.baseline, 100.0);
+        assert_eq!(knee.value, 200.0);
+    }
+
+    #[test]
+    fn a_single_slow_window_is_not_a_knee() {
+        let p95s = [100, 100, 100, 100, 300, 100, 300, 100];
+        assert!(latency_knee(&windows(&p95s.map(|p95| (p95, 0.0)))).is_none());
+    }
+
+    #[test]
+    fn needs_enough_busy_windows() {
+        let mut windows = windows(&[(100, 0.0), (100, 0.0), (100, 0.0), (900, 0.0), (900, 0.0)]);
+        assert!(latency_knee(&windows).is_some());
+        windows[0].requests = 0;
+        assert!(latency_knee(&windows).is_none());
+    }
+
+    #[test]
+    fn error_knee_is_an_increase_in_points() {
+        let rates = [1.0, 2.0, 1.0, 5.0, 7.0, 8.0];
+        let windows = windows(&rates.map(|rate| (100, rate)));
+        let knee = find_knee(
+            "error_rate",
+            &windows,
+            |w| w.error_rate,
+            |baseline| baseline + ERROR_KNEE_INCREASE,
+        )
+        .unwrap();
+        assert_eq!(knee.at, Duration::from_secs(4));
+    }
+
+    fn scenario(user_id: &str, requests: &[(&str, u64, u64)]) -> UserScenarioResult {
+        UserScenarioResult {
+            stage: "default".to_string(),
+            user_id: user_id.to_string(),
+            requests: requests
+                .iter()
+                .map(|&(step, started_ms, response_ms)| RequestResult {
+                    step: step.to_string(),
+                    success: true,
+                    started_at: Duration::from_millis(started_ms),
+                    response_time: Duration::from_millis(response_ms),
+                    ..RequestResult::default()
+                })
+                .collect(),
+            total_time: Duration::ZERO,
+            success: true,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn buckets_requests_and_active_users_by_window() {
+        let results = [
+            scenario("a", &[("search", 0, 100), ("adopt", 2500, 100)]),
+            scenario("b", &[("search", 1200, 100)]),
+        ];
+        let series = TimeSeries::from_scenarios(
+            &results,
+            Duration::from_secs(3),
+            Some(Duration::from_secs(1)),
+        );
+
+        let requests: Vec<usize> = series.run.iter().map(|w| w.requests).collect();
+        assert_eq!(requests, [1, 1, 1]);
+        let active: Vec<usize> = series.run.iter().map(|w| w.active_users).collect();
+        assert_eq!(active, [1, 2, 1]);
+        let steps: Vec<&str> = series.steps.iter().map(|s| s.step.as_str()).collect();
+        assert_eq!(steps, ["search", "adopt"]);
+        assert_eq!(series.steps[1].windows[2].requests, 1);
+    }
+
+    #[test]
+    fn auto_width_aims_for_sixty_windows() {
+        assert_eq!(auto_width(Duration::from_secs(30)), Duration::from_secs(1));
+        assert_eq!(
+            auto_width(Duration::from_secs(600)),
+            Duration::from_secs(10)
+        );
+        assert_eq!(
+            auto_width(Duration::from_secs(601)),
+            Duration::from_secs(11)
+        );
+    }
+}
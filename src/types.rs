@@ -1,65 +1,356 @@
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::time::Duration;
 
-#[derive(Debug, Clone)]
+use crate::errors::ErrorKind;
+
+/// Well-known service names used by the built-in PetSite scenario
+pub mod services {
+    pub const PETLISTADOPTIONS: &str = "petlistadoptions";
+    pub const PETSEARCH: &str = "petsearch";
+    pub const PAYFORADOPTION: &str = "payforadoption";
+    pub const PETFOOD: &str = "petfood";
+}
+
+/// Registry of service endpoints keyed by service name
+#[derive(Debug, Clone, Default)]
 pub struct Endpoints {
-    pub petlistadoptions: String,
-    pub petsearch: String,
-    pub payforadoption: String,
-    pub petfood: String,
+    services: BTreeMap<String, String>,
 }
 
 impl Endpoints {
+    /// Built-in localhost defaults, used for any service no provider resolves
     pub fn new() -> Self {
-        Self {
-            petlistadoptions: std::env::var("PETLIST_ENDPOINT")
-                .unwrap_or_else(|_| "http://localhost:8080".to_string()),
-            petsearch: std::env::var("PETSEARCH_ENDPOINT")
-                .unwrap_or_else(|_| "http://localhost:8081".to_string()),
-            payforadoption: std::env::var("PAYFORADOPTION_ENDPOINT")
-                .unwrap_or_else(|_| "http://localhost:8082".to_string()),
-            petfood: std::env::var("PETFOOD_ENDPOINT")
-                .unwrap_or_else(|_| "http://localhost:8083".to_string()),
+        let mut endpoints = Self::default();
+        for (service, url) in [
+            (services::PETLISTADOPTIONS, "http://localhost:8080"),
+            (services::PETSEARCH, "http://localhost:8081"),
+            (services::PAYFORADOPTION, "http://localhost:8082"),
+            (services::PETFOOD, "http://localhost:8083"),
+        ] {
+            endpoints
+                .services
+                .insert(service.to_string(), url.to_string());
         }
+        endpoints
+    }
+
+    /// Register or replace the URL for a service, rejecting URLs that don't parse
+    pub fn set(&mut self, service: &str, url: String) -> anyhow::Result<()> {
+        reqwest::Url::parse(&url)
+            .map_err(|err| anyhow::anyhow!("Invalid URL for {}: {} ({})", service, url, err))?;
+        self.services.insert(service.to_string(), url);
+        Ok(())
+    }
+
+    pub fn get(&self, service: &str) -> Option<&str> {
+        self.services.get(service).map(String::as_str)
+    }
+
+    /// Services from `required` that have no endpoint
+    pub fn missing<'a>(&self, required: &[&'a str]) -> Vec<&'a str> {
+        required
+            .iter()
+            .filter(|service| !self.services.contains_key(**service))
+            .copied()
+            .collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.services
+            .iter()
+            .map(|(service, url)| (service.as_str(), url.as_str()))
+    }
+}
+
+/// Serialize durations as fractional milliseconds in saved reports
+pub mod duration_ms {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(duration.as_secs_f64() * 1000.0)
     }
 
-    pub fn is_empty(&self) -> bool {
-        self.petlistadoptions.is_empty()
-            && self.petsearch.is_empty()
-            && self.payforadoption.is_empty()
-            && self.petfood.is_empty()
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let millis = f64::deserialize(deserializer)?;
+        Ok(Duration::from_secs_f64(millis.max(0.0) / 1000.0))
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct RequestResult {
+    /// Scenario step that issued the request
+    pub step: String,
     pub method: String,
     pub url: String,
     pub user_id: String,
     pub success: bool,
+    #[serde(with = "duration_ms")]
     pub response_time: Duration,
     pub status: u16,
     pub error: Option<String>,
+    #[serde(default)]
+    pub error_kind: Option<ErrorKind>,
+    /// Error kind of each earlier attempt that was retried; `response_time`,
+    /// `status` and `error` describe the final attempt
+    #[serde(default)]
+    pub retries: Vec<ErrorKind>,
+    /// Time spent waiting on the rate limiter, across all attempts; not part of `response_time`
+    #[serde(with = "duration_ms", default)]
+    pub limiter_wait: Duration,
+    /// When the request was sent, relative to the start of the run
+    #[serde(with = "duration_ms", default)]
+    pub started_at: Duration,
+    /// Time the final attempt spent opening new connections, across redirect hops;
+    /// part of `response_time`, which stays the total so baselines remain comparable
+    #[serde(with = "duration_ms", default)]
+    pub connect_time: Duration,
 }
 
-#[derive(Debug)]
+impl RequestResult {
+    /// Why the request failed, inferred from the status for reports saved before
+    /// errors were classified
+    pub fn failure_kind(&self) -> Option<ErrorKind> {
+        if self.success {
+            None
+        } else if let Some(kind) = self.error_kind {
+            Some(kind)
+        } else if self.status > 0 {
+            Some(ErrorKind::Http(self.status))
+        } else if self.error.as_deref() == Some("Request timeout") {
+            Some(ErrorKind::RequestTimeout)
+        } else {
+            Some(ErrorKind::Other)
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct UserScenarioResult {
+    /// Load stage the scenario ran in
+    pub stage: String,
     pub user_id: String,
     pub requests: Vec<RequestResult>,
+    #[serde(with = "duration_ms")]
     pub total_time: Duration,
     pub success: bool,
     pub error: Option<String>,
 }
 
-#[derive(Debug)]
+/// Nearest-rank percentile of an ascending list of durations
+pub fn percentile(sorted: &[Duration], pct: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn average_limiter_wait(requests: &[&RequestResult]) -> Duration {
+    if requests.is_empty() {
+        Duration::ZERO
+    } else {
+        requests.iter().map(|r| r.limiter_wait).sum::<Duration>() / requests.len() as u32
+    }
+}
+
+/// Aggregates for every request issued by one scenario step
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepStats {
+    pub step: String,
+    pub total_requests: usize,
+    pub failed_requests: usize,
+    /// Percentage of requests that failed
+    pub error_rate: f64,
+    pub requests_per_second: f64,
+    #[serde(with = "duration_ms")]
+    pub average_response_time: Duration,
+    #[serde(with = "duration_ms")]
+    pub p50_response_time: Duration,
+    #[serde(with = "duration_ms")]
+    pub p95_response_time: Duration,
+    #[serde(with = "duration_ms")]
+    pub p99_response_time: Duration,
+    /// Failed requests by error kind
+    #[serde(default)]
+    pub errors: BTreeMap<ErrorKind, usize>,
+    /// Extra attempts made by the retry policy
+    #[serde(default)]
+    pub retries: usize,
+    /// Requests that failed at first but succeeded on a retry
+    #[serde(default)]
+    pub recovered_requests: usize,
+    /// Errors from retried attempts, which never count as failures
+    #[serde(default)]
+    pub retried_errors: BTreeMap<ErrorKind, usize>,
+    #[serde(with = "duration_ms", default)]
+    pub average_limiter_wait: Duration,
+}
+
+impl StepStats {
+    fn from_requests(step: &str, requests: &[&RequestResult], total_time: Duration) -> Self {
+        let total_requests = requests.len();
+        let failed_requests = requests.iter().filter(|r| !r.success).count();
+
+        // Like the overall average, latency figures only include successful requests
+        let mut response_times: Vec<Duration> = requests
+            .iter()
+            .filter(|r| r.success)
+            .map(|r| r.response_time)
+            .collect();
+        response_times.sort();
+
+        let average_response_time = if !response_times.is_empty() {
+            response_times.iter().sum::<Duration>() / response_times.len() as u32
+        } else {
+            Duration::from_millis(0)
+        };
+
+        let mut errors = BTreeMap::new();
+        for kind in requests.iter().filter_map(|r| r.failure_kind()) {
+            *errors.entry(kind).or_default() += 1;
+        }
+        let mut retried_errors = BTreeMap::new();
+        for kind in requests.iter().flat_map(|r| &r.retries) {
+            *retried_errors.entry(*kind).or_default() += 1;
+        }
+
+        Self {
+            step: step.to_string(),
+            total_requests,
+            failed_requests,
+            error_rate: if total_requests > 0 {
+                (failed_requests as f64 / total_requests as f64) * 100.0
+            } else {
+                0.0
+            },
+            requests_per_second: if total_time.as_secs_f64() > 0.0 {
+                total_requests as f64 / total_time.as_secs_f64()
+            } else {
+                0.0
+            },
+            average_response_time,
+            p50_response_time: percentile(&response_times, 50.0),
+            p95_response_time: percentile(&response_times, 95.0),
+            p99_response_time: percentile(&response_times, 99.0),
+            errors,
+            retries: requests.iter().map(|r| r.retries.len()).sum(),
+            recovered_requests: requests
+                .iter()
+                .filter(|r| r.success && !r.retries.is_empty())
+                .count(),
+            retried_errors,
+            average_limiter_wait: average_limiter_wait(requests),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LoadTestResults {
     pub total_requests: usize,
     pub successful_requests: usize,
     pub failed_requests: usize,
+    #[serde(with = "duration_ms")]
     pub average_response_time: Duration,
+    #[serde(with = "duration_ms")]
+    pub p50_response_time: Duration,
+    #[serde(with = "duration_ms")]
+    pub p95_response_time: Duration,
+    #[serde(with = "duration_ms")]
+    pub p99_response_time: Duration,
+    #[serde(with = "duration_ms")]
     pub total_test_time: Duration,
     pub requests_per_second: f64,
     pub success_rate: f64,
+    /// Extra attempts made by the retry policy
+    #[serde(default)]
+    pub retries: usize,
+    /// Requests that failed at first but succeeded on a retry
+    #[serde(default)]
+    pub recovered_requests: usize,
+    /// Time requests spent waiting on the rate limiter
+    #[serde(with = "duration_ms", default)]
+    pub total_limiter_wait: Duration,
+    #[serde(with = "duration_ms", default)]
+    pub average_limiter_wait: Duration,
+    /// Per-step aggregates, in the order steps first ran
+    pub steps: Vec<StepStats>,
+}
+
+impl LoadTestResults {
+    pub fn from_scenarios(results: &[UserScenarioResult], total_time: Duration) -> Self {
+        let all_requests: Vec<&RequestResult> = results.iter().flat_map(|r| &r.requests).collect();
+
+        let total_requests = all_requests.len();
+        let successful_requests = all_requests.iter().filter(|r| r.success).count();
+        let failed_requests = total_requests - successful_requests;
+
+        let mut successful_response_times: Vec<Duration> = all_requests
+            .iter()
+            .filter(|r| r.success)
+            .map(|r| r.response_time)
+            .collect();
+        successful_response_times.sort();
+
+        let average_response_time = if !successful_response_times.is_empty() {
+            successful_response_times.iter().sum::<Duration>()
+                / successful_response_times.len() as u32
+        } else {
+            Duration::from_millis(0)
+        };
+
+        let requests_per_second = if total_time.as_secs_f64() > 0.0 {
+            total_requests as f64 / total_time.as_secs_f64()
+        } else {
+            0.0
+        };
+
+        let success_rate = if total_requests > 0 {
+            (successful_requests as f64 / total_requests as f64) * 100.0
+        } else {
+            0.0
+        };
+
+        let mut step_order: Vec<&str> = Vec::new();
+        let mut by_step: HashMap<&str, Vec<&RequestResult>> = HashMap::new();
+        for request in &all_requests {
+            let entry = by_step.entry(request.step.as_str()).or_insert_with(|| {
+                step_order.push(request.step.as_str());
+                Vec::new()
+            });
+            entry.push(request);
+        }
+        let steps = step_order
+            .iter()
+            .map(|step| StepStats::from_requests(step, &by_step[step], total_time))
+            .collect();
+
+        Self {
+            total_requests,
+            successful_requests,
+            failed_requests,
+            average_response_time,
+            p50_response_time: percentile(&successful_response_times, 50.0),
+            p95_response_time: percentile(&successful_response_times, 95.0),
+            p99_response_time: percentile(&successful_response_times, 99.0),
+            total_test_time: total_time,
+            requests_per_second,
+            success_rate,
+            retries: all_requests.iter().map(|r| r.retries.len()).sum(),
+            recovered_requests: all_requests
+                .iter()
+                .filter(|r| r.success && !r.retries.is_empty())
+                .count(),
+            total_limiter_wait: all_requests.iter().map(|r| r.limiter_wait).sum(),
+            average_limiter_wait: average_limiter_wait(&all_requests),
+            steps,
+        }
+    }
+
+    pub fn step(&self, name: &str) -> Option<&StepStats> {
+        self.steps.iter().find(|step| step.step == name)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -76,12 +367,4 @@ pub struct Pet {
 // The API returns an array of pets directly, not wrapped in an object
 pub type PetListResponse = Vec<Pet>;
 
-// Adoption is now done via query parameters, not JSON payload
-#[derive(Debug)]
-pub struct AdoptionRequest {
-    pub pet_id: String,
-    pub pet_type: String,
-    pub user_id: String,
-}
-
 // Food cart operations now use dynamic JSON payloads via serde_json::json!
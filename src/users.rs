@@ -0,0 +1,212 @@
+use clap::ValueEnum;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use crate::seed;
+
+/// Ids the `random` strategy draws from: `user10000` to `user99999`
+const RANDOM_ID_RANGE: std::ops::RangeInclusive<u32> = 10000..=99999;
+
+/// How virtual user ids are made:
+///
+/// ```toml
+/// [user_ids]
+/// strategy = "sequence"
+/// prefix = "loadtest"
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UserIdConfig {
+    pub strategy: UserIdStrategy,
+    /// Starts every generated id; unused by the `file` strategy
+    pub prefix: String,
+    /// One user id per line, for the `file` strategy
+    pub file: Option<PathBuf>,
+}
+
+impl Default for UserIdConfig {
+    fn default() -> Self {
+        Self {
+            strategy: UserIdStrategy::default(),
+            prefix: "user".to_string(),
+            file: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UserIdStrategy {
+    /// `{prefix}-{run_id}-{n}`, numbered from 1; never shared with another run
+    #[default]
+    Sequence,
+    /// `{prefix}{NNNNN}` from 10000-99999, drawn from the seed and run id; with
+    /// so few ids, another run can still pick some of the same ones
+    Random,
+    /// `{prefix}-{uuid}`, drawn from the seed and run id
+    Uuid,
+    /// Ids read from `file`, in order, e.g. pre-provisioned accounts
+    File,
+}
+
+impl UserIdConfig {
+    /// Fail unless the strategy can give `total_users` distinct ids
+    pub fn validate(&self, total_users: usize) -> anyhow::Result<()> {
+        if !is_id_safe(&self.prefix) {
+            anyhow::bail!("user_ids.prefix may only contain letters, digits, '-' and '_'");
+        }
+        match self.strategy {
+            UserIdStrategy::Random => {
+                let space = RANDOM_ID_RANGE.count();
+                if total_users > space {
+                    anyhow::bail!(
+                        "The random user id strategy has {} ids but the run needs {} users; use strategy = \"sequence\" or \"uuid\"",
+                        space,
+                        total_users
+                    );
+                }
+            }
+            UserIdStrategy::File if self.file.is_none() => {
+                anyhow::bail!("user_ids.strategy = \"file\" needs user_ids.file");
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// `count` distinct user ids, the same for a given seed and run id
+    pub fn generate(&self, count: usize, seed: u64, run_id: &str) -> anyhow::Result<Vec<String>> {
+        // The run id keeps two runs with the same seed from drawing the same ids
+        let mut rng = seed::rng_for(seed, &["users", run_id]);
+        match self.strategy {
+            UserIdStrategy::Sequence => Ok((1..=count)
+                .map(|n| format!("{}-{}-{:05}", self.prefix, run_id, n))
+                .collect()),
+            UserIdStrategy::Random => {
+                let mut seen = HashSet::new();
+                let mut user_ids = Vec::with_capacity(count);
+                // Kept in the order drawn so a seed gives the same list
+                while user_ids.len() < count {
+                    let user_id = format!("{}{:05}", self.prefix, rng.gen_range(RANDOM_ID_RANGE));
+                    if seen.insert(user_id.clone()) {
+                        user_ids.push(user_id);
+                    }
+                }
+                Ok(user_ids)
+            }
+            UserIdStrategy::Uuid => Ok((0..count)
+                .map(|_| {
+                    let uuid = uuid::Builder::from_random_bytes(rng.gen()).into_uuid();
+                    format!("{}-{}", self.prefix, uuid)
+                })
+                .collect()),
+            UserIdStrategy::File => {
+                let path = self.file.as_deref().ok_or_else(|| {
+                    anyhow::anyhow!("user_ids.strategy = \"file\" needs user_ids.file")
+                })?;
+                let text = std::fs::read_to_string(path)
+                    .map_err(|err| anyhow::anyhow!("Failed to read {}: {}", path.display(), err))?;
+                let user_ids = ids_from_lines(&text, count)
+                    .map_err(|err| anyhow::anyhow!("{}: {}", path.display(), err))?;
+                if user_ids.len() < count {
+                    anyhow::bail!(
+                        "{} lists {} distinct user ids but the run needs {}",
+                        path.display(),
+                        user_ids.len(),
+                        count
+                    );
+                }
+                Ok(user_ids)
+            }
+        }
+    }
+}
+
+/// Up to `count` distinct ids from an id file, skipping blank and `#` lines
+fn ids_from_lines(text: &str, count: usize) -> anyhow::Result<Vec<String>> {
+    let mut seen = HashSet::new();
+    let mut user_ids = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if !is_id_safe(line) {
+            anyhow::bail!(
+                "line {}: user id {:?} may only contain letters, digits, '-' and '_'",
+                index + 1,
+                line
+            );
+        }
+        if user_ids.len() < count && seen.insert(line) {
+            user_ids.push(line.to_string());
+        }
+    }
+    Ok(user_ids)
+}
+
+/// A fresh run id for runs started without one: 8 hex digits
+pub fn new_run_id() -> String {
+    uuid::Uuid::new_v4().simple().to_string()[..8].to_string()
+}
+
+/// Whether `text` can go into a URL path or query unescaped
+pub fn is_id_safe(text: &str) -> bool {
+    text.chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(strategy: UserIdStrategy) -> UserIdConfig {
+        UserIdConfig {
+            strategy,
+            ..UserIdConfig::default()
+        }
+    }
+
+    #[test]
+    fn sequence_ids_are_numbered_within_the_run() {
+        let ids = config(UserIdStrategy::Sequence)
+            .generate(2, 7, "run1")
+            .unwrap();
+        assert_eq!(ids, ["user-run1-00001", "user-run1-00002"]);
+    }
+
+    #[test]
+    fn drawn_ids_follow_the_seed_and_run_id() {
+        for strategy in [UserIdStrategy::Random, UserIdStrategy::Uuid] {
+            let config = config(strategy);
+            let ids = config.generate(50, 7, "run1").unwrap();
+            assert_eq!(ids, config.generate(50, 7, "run1").unwrap());
+            assert_ne!(ids, config.generate(50, 8, "run1").unwrap());
+            assert_ne!(ids, config.generate(50, 7, "run2").unwrap());
+            assert_eq!(ids.iter().collect::<HashSet<_>>().len(), 50);
+            assert!(ids.iter().all(|id| is_id_safe(id)));
+        }
+    }
+
+    #[test]
+    fn random_strategy_rejects_more_users_than_ids() {
+        let config = config(UserIdStrategy::Random);
+        assert!(config.validate(RANDOM_ID_RANGE.count()).is_ok());
+        assert!(config.validate(RANDOM_ID_RANGE.count() + 1).is_err());
+    }
+
+    #[test]
+    fn id_file_skips_comments_blanks_and_repeats() {
+        let text = "# accounts\nalice\n\n  bob  \nalice\ncarol\n";
+        assert_eq!(ids_from_lines(text, 2).unwrap(), ["alice", "bob"]);
+        assert_eq!(ids_from_lines(text, 5).unwrap(), ["alice", "bob", "carol"]);
+    }
+
+    #[test]
+    fn id_file_rejects_unsafe_ids_with_their_line() {
+        let err = ids_from_lines("alice\n# note\nbob smith\n", 5).unwrap_err();
+        assert!(err.to_string().starts_with("line 3:"), "{}", err);
+    }
+}